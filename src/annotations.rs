@@ -0,0 +1,29 @@
+use guh_chess::square::Square;
+
+/// A mark drawn on the board with the right mouse button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Annotation {
+    Circle(Square),
+    /// From the first square to the second
+    Arrow(Square, Square),
+}
+
+impl Annotation {
+    /// A circle if the drag ended on the square it started on, an arrow otherwise
+    pub fn between(start: Square, end: Square) -> Self {
+        if start == end {
+            Annotation::Circle(start)
+        } else {
+            Annotation::Arrow(start, end)
+        }
+    }
+}
+
+/// Adds the annotation, drawing one that is already there again removes it
+pub fn toggle_annotation(annotations: &mut Vec<Annotation>, annotation: Annotation) {
+    if let Some(index) = annotations.iter().position(|a| *a == annotation) {
+        annotations.remove(index);
+    } else {
+        annotations.push(annotation);
+    }
+}
@@ -0,0 +1,5 @@
+//! The built-in engine as a UCI engine for other chess GUIs, talking over stdin and stdout
+
+fn main() {
+    guh_chess::uci::run();
+}
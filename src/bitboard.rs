@@ -0,0 +1,333 @@
+use std::sync::OnceLock;
+
+use crate::{board::Color, square::Square};
+
+/// A set of squares, bit `n` is set if the square with index `n` is in the set
+pub type Bitboard = u64;
+
+pub fn square_bit(square: Square) -> Bitboard {
+    1 << square.index()
+}
+
+/// Iterates over the squares in the bitboard, from the lowest index to the highest
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        // an empty bitboard has 64 trailing zeros, which is not a square
+        let square = Square::from_index(bitboard.trailing_zeros() as u8)?;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    tables().knight[square.index()]
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    tables().king[square.index()]
+}
+
+/// The squares a pawn of `color` standing on `square` attacks
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    tables().pawn[color as usize][square.index()]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.rook[square.index()].lookup(&tables.sliding, occupied)
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.bishop[square.index()].lookup(&tables.sliding, occupied)
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+/// `(files, ranks)` offsets, see `Square::offset`
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, -1), (-1, 1), (1, -1)];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (2, 1),
+    (1, 2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, 2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, -1),
+    (0, 1),
+    (1, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+];
+
+/// Magic bitboard entry of a single square. The relevant occupancy is multiplied by the
+/// magic number and shifted, which gives a unique index into the attack table for every
+/// occupancy that leads to different attacks.
+#[derive(Default, Clone, Copy)]
+struct Magic {
+    /// The squares whose occupancy changes the attacks, the board edges excluded
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn lookup(&self, table: &[Bitboard], occupied: Bitboard) -> Bitboard {
+        let index = ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize;
+        table[self.offset + index]
+    }
+}
+
+struct AttackTables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    pawn: [[Bitboard; 64]; 2],
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    /// The sliding attacks of all squares for rooks and bishops, see `Magic::offset`
+    sliding: Vec<Bitboard>,
+}
+
+fn tables() -> &'static AttackTables {
+    static TABLES: OnceLock<AttackTables> = OnceLock::new();
+    TABLES.get_or_init(AttackTables::new)
+}
+
+impl AttackTables {
+    fn new() -> Self {
+        let mut tables = Self {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            rook: [Magic::default(); 64],
+            bishop: [Magic::default(); 64],
+            sliding: Vec::new(),
+        };
+        for square in Square::all() {
+            let i = square.index();
+            tables.knight[i] = offset_squares(square, &KNIGHT_OFFSETS);
+            tables.king[i] = offset_squares(square, &KING_OFFSETS);
+            tables.pawn[Color::White as usize][i] = offset_squares(square, &[(-1, 1), (1, 1)]);
+            tables.pawn[Color::Black as usize][i] = offset_squares(square, &[(-1, -1), (1, -1)]);
+            tables.rook[i] = tables.add_sliding_attacks(square, &ROOK_DIRECTIONS, ROOK_MAGICS[i]);
+            tables.bishop[i] =
+                tables.add_sliding_attacks(square, &BISHOP_DIRECTIONS, BISHOP_MAGICS[i]);
+        }
+        tables
+    }
+
+    /// Fills the attack table of the square for every relevant occupancy
+    fn add_sliding_attacks(
+        &mut self,
+        square: Square,
+        directions: &[(i8, i8)],
+        magic_number: u64,
+    ) -> Magic {
+        let mask = sliding_mask(square, directions);
+        let bits = mask.count_ones();
+        let magic = Magic {
+            mask,
+            magic: magic_number,
+            shift: 64 - bits,
+            offset: self.sliding.len(),
+        };
+        self.sliding.resize(self.sliding.len() + (1 << bits), 0);
+        // walk through all subsets of the mask
+        let mut occupied: Bitboard = 0;
+        loop {
+            let index_in_table = (occupied.wrapping_mul(magic_number) >> magic.shift) as usize;
+            self.sliding[magic.offset + index_in_table] =
+                sliding_attacks_slow(square, occupied, directions);
+            occupied = occupied.wrapping_sub(mask) & mask;
+            if occupied == 0 {
+                break;
+            }
+        }
+        magic
+    }
+}
+
+fn offset_squares(square: Square, offsets: &[(i8, i8)]) -> Bitboard {
+    offsets
+        .iter()
+        .filter_map(|(files, ranks)| square.offset(*files, *ranks))
+        .fold(0, |bitboard, target| bitboard | square_bit(target))
+}
+
+/// Walks every direction until the edge of the board or an occupied square, which is included
+fn sliding_attacks_slow(square: Square, occupied: Bitboard, directions: &[(i8, i8)]) -> Bitboard {
+    let mut bitboard = 0;
+    for (files, ranks) in directions {
+        let mut current = square.offset(*files, *ranks);
+        while let Some(target) = current {
+            bitboard |= square_bit(target);
+            if occupied & square_bit(target) != 0 {
+                break;
+            }
+            current = target.offset(*files, *ranks);
+        }
+    }
+    bitboard
+}
+
+/// The squares a slider could be blocked on, which are all attacked squares on an empty
+/// board except the last one in every direction
+fn sliding_mask(square: Square, directions: &[(i8, i8)]) -> Bitboard {
+    let mut bitboard = 0;
+    for (files, ranks) in directions {
+        let mut current = square.offset(*files, *ranks);
+        while let Some(target) = current {
+            current = target.offset(*files, *ranks);
+            if current.is_some() {
+                bitboard |= square_bit(target);
+            }
+        }
+    }
+    bitboard
+}
+
+// Magic numbers for the square order of `Square`, found by trying random sparse numbers
+// until all occupancies of a square map to indices without destructive collisions.
+const ROOK_MAGICS: [u64; 64] = [
+    0x0080102080004000,
+    0x204000200010004a,
+    0x1080200010000880,
+    0x0480044800801000,
+    0x2280280002800400,
+    0x0500020881004400,
+    0x0200388902000408,
+    0x028002c221800100,
+    0x8408800080400031,
+    0x00014000a0005000,
+    0x0005803002600080,
+    0x1080800800100080,
+    0x2049808004002800,
+    0x0001000401000802,
+    0x2849000100020004,
+    0x0419800100025180,
+    0x9008888000400224,
+    0x0410004000200041,
+    0x00a0030040210210,
+    0x6008008080081000,
+    0x000c050008005100,
+    0x034a808004000201,
+    0x0001040001881002,
+    0x0000520004148045,
+    0x0020802080004000,
+    0x8001400180200088,
+    0x0100148200204200,
+    0x0200090100100420,
+    0x0080080080800400,
+    0x0008040080020080,
+    0xa001010400100208,
+    0x4078040200288051,
+    0x2080004000402000,
+    0x0028402000401001,
+    0x0120100080802000,
+    0x0800200a02004010,
+    0x0200800c01800800,
+    0x0820040080800200,
+    0x0000800200800100,
+    0x0c07000041000082,
+    0x4040400020808002,
+    0x0082010020820044,
+    0x0030200010008080,
+    0x0090008008008010,
+    0x8008000804008080,
+    0xf032000488820010,
+    0x0201081110540002,
+    0x7400c08041020004,
+    0x00008012400c2080,
+    0x01c0400020810100,
+    0x0040200090008180,
+    0x8448800800100080,
+    0x0000100408010100,
+    0x0000020080040080,
+    0x0084100812610400,
+    0x0020108400410a00,
+    0x8002220081401902,
+    0x0000238500400015,
+    0x020a001282084022,
+    0x0480041000082101,
+    0x0102000409102002,
+    0x042d00140002088b,
+    0x940400902a281104,
+    0x4003002401088042,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x01043008a1040080,
+    0x40ec088800458000,
+    0x10210204820c0040,
+    0x0010908208200180,
+    0x4021104005010000,
+    0x8000901008010081,
+    0x0000a09010100009,
+    0x3000208804108200,
+    0x00014002280200a0,
+    0x0200200200b10100,
+    0x0120102080810000,
+    0x0086224081000000,
+    0x0106811040441010,
+    0x8010009004200a00,
+    0x2801008088084008,
+    0x8102102401045020,
+    0x0521044002048100,
+    0x4108080202280228,
+    0x8090052082254041,
+    0x0001005024008440,
+    0x4204000220a00205,
+    0x4800801900514014,
+    0x6210804908384202,
+    0x0808814028880824,
+    0x0011052040082207,
+    0x8004a03812062400,
+    0x040804048a023202,
+    0x0004040000410200,
+    0x0212840100802010,
+    0x0810010013804111,
+    0x4c4821201c808801,
+    0x0904410002009a00,
+    0x2010300800120250,
+    0x090401a000842404,
+    0x0042402808101440,
+    0x0400020080a80080,
+    0x4100440440040100,
+    0x0400900480050080,
+    0x0024010200a04820,
+    0x1004090030884400,
+    0x0205101024401208,
+    0x0000420820000500,
+    0x0101202110002040,
+    0x4048012011010800,
+    0x0008022009001a00,
+    0x0440014041000080,
+    0x0010108080840110,
+    0x0001041102101049,
+    0x200a480858488204,
+    0x0502046c02080611,
+    0x1030020100b80125,
+    0x008000c242020402,
+    0x000a010410440204,
+    0x0020e02142108040,
+    0x00c1142882104046,
+    0x078218b811004088,
+    0xa000904c10080854,
+    0xa408210100904401,
+    0x200004809c008800,
+    0x012010000a420880,
+    0x0000001210020208,
+    0x4792801120012102,
+    0x0180109081080082,
+    0x4408204100490100,
+];
@@ -1,17 +1,33 @@
-use std::vec;
+use crate::{
+    bitboard::{Bitboard, square_bit},
+    square::{File, Rank, Square},
+};
 
-use eframe::egui::Pos2;
-
-use crate::{BOARD_SQUARE_SIZE, BOARD_SQUARES};
-
-#[derive(Default, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum Color {
     #[default]
     White,
     Black,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    /// The rank offset of a pawn move of the color, see `Square::offset`
+    pub fn pawn_direction(&self) -> i8 {
+        match self {
+            Color::White => 1,
+            Color::Black => -1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -21,7 +37,29 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Clone, Copy)]
+impl PieceType {
+    /// The usual material value in pawns, the king has none
+    pub fn points(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+        }
+    }
+}
+
+pub const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Piece {
     pub color: Color,
     pub piece_type: PieceType,
@@ -29,23 +67,48 @@ pub struct Piece {
 
 impl Piece {
     pub fn new(piece_type: PieceType, color: Color) -> Self {
-        Self {
-            color: color,
-            piece_type: piece_type,
-        }
+        Self { color, piece_type }
     }
 }
 
-pub type Board = Vec<Option<Piece>>;
+/// The pieces on the board as bitboards, see `Bitboard`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+    /// One bitboard per piece type, indexed by `PieceType as usize`
+    pub pieces: [Bitboard; 6],
+    /// One bitboard per color, indexed by `Color as usize`
+    pub colors: [Bitboard; 2],
+}
+
+impl Board {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[Color::White as usize] | self.colors[Color::Black as usize]
+    }
+
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    /// The squares of all pieces of the type and color
+    pub fn pieces_of(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        self.pieces[piece_type as usize] & self.colors[color as usize]
+    }
+}
 
 pub trait BoardExt {
     fn default_position() -> Board;
-    fn piece_at(&self, index: BoardIndex) -> Option<Piece>;
+    fn piece_at(&self, square: Square) -> Option<Piece>;
+    /// Puts the piece on the square, replacing whatever stood there, or clears it for `None`
+    fn set_piece(&mut self, square: Square, piece: Option<Piece>);
 }
 
 impl BoardExt for Board {
     fn default_position() -> Board {
-        let mut board = vec![None; (BOARD_SQUARES * BOARD_SQUARES) as usize];
+        let mut board = Board::empty();
         let row = [
             PieceType::Rook,
             PieceType::Knight,
@@ -57,52 +120,50 @@ impl BoardExt for Board {
             PieceType::Rook,
         ];
 
-        board[0..8].copy_from_slice(&row.map(|p| Some(Piece::new(p, Color::Black))));
-        board[8..16].fill(Some(Piece::new(PieceType::Pawn, Color::Black)));
-        board[16..48].fill(None);
-        board[48..56].fill(Some(Piece::new(PieceType::Pawn, Color::White)));
-        board[56..64].copy_from_slice(&row.map(|p| Some(Piece::new(p, Color::White))));
+        for (file, piece_type) in File::all().zip(row) {
+            board.set_piece(
+                Square::new(file, Rank::EIGHTH),
+                Some(Piece::new(piece_type, Color::Black)),
+            );
+            board.set_piece(
+                Square::new(file, Rank::SEVENTH),
+                Some(Piece::new(PieceType::Pawn, Color::Black)),
+            );
+            board.set_piece(
+                Square::new(file, Rank::SECOND),
+                Some(Piece::new(PieceType::Pawn, Color::White)),
+            );
+            board.set_piece(
+                Square::new(file, Rank::FIRST),
+                Some(Piece::new(piece_type, Color::White)),
+            );
+        }
         board
     }
 
-    fn piece_at(&self, index: BoardIndex) -> Option<Piece> {
-        let index = index as usize;
-        if index >= self.len() {
-            None
+    fn piece_at(&self, square: Square) -> Option<Piece> {
+        let bit = square_bit(square);
+        let color = if self.colors[Color::White as usize] & bit != 0 {
+            Color::White
+        } else if self.colors[Color::Black as usize] & bit != 0 {
+            Color::Black
         } else {
-            self[index]
-        }
-    }
-}
-
-pub type BoardIndex = u16;
-pub type BoardIndexXY = (u16, u16);
-
-pub trait BoardIndexExt {
-    fn from_screen_click(pos: Pos2) -> Self;
-    fn to_xy(&self) -> BoardIndexXY;
-}
-
-impl BoardIndexExt for BoardIndex {
-    fn from_screen_click(pos: Pos2) -> Self {
-        let xy: (u16, u16) = (
-            (pos.x / BOARD_SQUARE_SIZE as f32).floor() as u16,
-            (pos.y / BOARD_SQUARE_SIZE as f32).floor() as u16,
-        );
-        xy.1 * BOARD_SQUARES + xy.0
-    }
-
-    fn to_xy(&self) -> BoardIndexXY {
-        (self % BOARD_SQUARES, self / BOARD_SQUARES)
+            return None;
+        };
+        let piece_type = PIECE_TYPES
+            .into_iter()
+            .find(|piece_type| self.pieces[*piece_type as usize] & bit != 0)?;
+        Some(Piece::new(piece_type, color))
     }
-}
-
-pub trait BoardIndexXYExt {
-    fn to_index(&self) -> BoardIndex;
-}
 
-impl BoardIndexXYExt for BoardIndexXY {
-    fn to_index(&self) -> BoardIndex {
-        BOARD_SQUARES * self.1 + self.0
+    fn set_piece(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = square_bit(square);
+        for bitboard in self.pieces.iter_mut().chain(self.colors.iter_mut()) {
+            *bitboard &= !bit;
+        }
+        if let Some(piece) = piece {
+            self.pieces[piece.piece_type as usize] |= bit;
+            self.colors[piece.color as usize] |= bit;
+        }
     }
 }
@@ -0,0 +1,116 @@
+use std::time::{Duration, Instant};
+
+use crate::board::Color;
+
+/// How a side's time is topped up for every move
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControlMode {
+    /// The increment is added after every move
+    #[default]
+    Fischer,
+    /// The time used for a move is given back after it, up to the delay
+    Bronstein,
+    /// The clock only starts running once the delay has passed
+    SimpleDelay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeControl {
+    /// The time each side starts with
+    pub base: Duration,
+    /// The increment or delay per move, depending on the mode
+    pub increment: Duration,
+    pub mode: TimeControlMode,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration, increment: Duration, mode: TimeControlMode) -> Self {
+        Self {
+            base,
+            increment,
+            mode,
+        }
+    }
+}
+
+/// The clocks of both sides, at most one of them runs at a time
+#[derive(Debug, Clone, Copy)]
+pub struct ChessClock {
+    pub time_control: TimeControl,
+    /// The time left per side, indexed by `Color as usize`, not counting the running move
+    remaining: [Duration; 2],
+    /// The side whose clock runs and when its move started
+    running: Option<(Color, Instant)>,
+}
+
+impl ChessClock {
+    /// Creates stopped clocks with the base time on both sides
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            time_control,
+            remaining: [time_control.base; 2],
+            running: None,
+        }
+    }
+
+    /// The time the side has left at `now`
+    pub fn remaining(&self, color: Color, now: Instant) -> Duration {
+        let remaining = self.remaining[color as usize];
+        match self.running {
+            Some((running, since)) if running == color => {
+                remaining.saturating_sub(self.charged_time(now.saturating_duration_since(since)))
+            }
+            _ => remaining,
+        }
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// `true` if the side ran out of time
+    pub fn is_flagged(&self, color: Color, now: Instant) -> bool {
+        self.remaining(color, now).is_zero()
+    }
+
+    /// Starts the clock of the side, stopping the other one without an increment
+    pub fn start(&mut self, color: Color, now: Instant) {
+        self.stop(now);
+        self.running = Some((color, now));
+    }
+
+    /// Stops the running clock, keeping the time it used
+    pub fn stop(&mut self, now: Instant) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color as usize] = self.remaining(color, now);
+            self.running = None;
+        }
+    }
+
+    /// Ends the move of the side whose clock runs, adds the increment or the delay the
+    /// mode gives back and starts the clock of the other side
+    pub fn press(&mut self, now: Instant) {
+        let Some((color, since)) = self.running else {
+            return;
+        };
+        let used = now.saturating_duration_since(since);
+        let mut remaining = self.remaining(color, now);
+        if !remaining.is_zero() {
+            remaining += match self.time_control.mode {
+                TimeControlMode::Fischer => self.time_control.increment,
+                TimeControlMode::Bronstein => used.min(self.time_control.increment),
+                TimeControlMode::SimpleDelay => Duration::ZERO,
+            };
+        }
+        self.remaining[color as usize] = remaining;
+        self.running = Some((color.opposite(), now));
+    }
+
+    /// The part of the time used for the current move that is taken off the clock
+    fn charged_time(&self, used: Duration) -> Duration {
+        match self.time_control.mode {
+            TimeControlMode::SimpleDelay => used.saturating_sub(self.time_control.increment),
+            TimeControlMode::Fischer | TimeControlMode::Bronstein => used,
+        }
+    }
+}
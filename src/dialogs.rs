@@ -0,0 +1,127 @@
+use eframe::egui;
+
+use guh_chess::pgn::PgnTags;
+
+pub enum DialogResult {
+    Open,
+    Confirmed,
+    Cancelled,
+}
+
+pub struct SavePgnDialog {
+    pub path: String,
+    pub tags: PgnTags,
+}
+
+impl SavePgnDialog {
+    pub fn new() -> Self {
+        Self {
+            path: "game.pgn".to_string(),
+            tags: PgnTags::default(),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> DialogResult {
+        let mut result = DialogResult::Open;
+        egui::Window::new("Save PGN")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("save_pgn_tags").show(ui, |ui| {
+                    for (label, value) in [
+                        ("Event", &mut self.tags.event),
+                        ("Site", &mut self.tags.site),
+                        ("Date", &mut self.tags.date),
+                        ("Round", &mut self.tags.round),
+                        ("White", &mut self.tags.white),
+                        ("Black", &mut self.tags.black),
+                        ("File", &mut self.path),
+                    ] {
+                        ui.label(label);
+                        ui.text_edit_singleline(value);
+                        ui.end_row();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        result = DialogResult::Confirmed;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        result = DialogResult::Cancelled;
+                    }
+                });
+            });
+        result
+    }
+}
+
+pub struct OpenPgnDialog {
+    pub path: String,
+}
+
+impl OpenPgnDialog {
+    pub fn new() -> Self {
+        Self {
+            path: "game.pgn".to_string(),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> DialogResult {
+        let mut result = DialogResult::Open;
+        egui::Window::new("Open PGN")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File");
+                    ui.text_edit_singleline(&mut self.path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Open").clicked() {
+                        result = DialogResult::Confirmed;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        result = DialogResult::Cancelled;
+                    }
+                });
+            });
+        result
+    }
+}
+
+pub struct LoadEngineDialog {
+    pub path: String,
+}
+
+impl LoadEngineDialog {
+    pub fn new() -> Self {
+        Self {
+            path: String::new(),
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> DialogResult {
+        let mut result = DialogResult::Open;
+        egui::Window::new("Load UCI engine")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Executable");
+                    ui.text_edit_singleline(&mut self.path);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        result = DialogResult::Confirmed;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        result = DialogResult::Cancelled;
+                    }
+                });
+            });
+        result
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    bitboard::squares,
+    board::{Board, BoardExt, Color, PieceType},
+    move_validation::validator::generate_piece_map,
+    square::Square,
+    state::{AdditionalBoardData, CastlingStatus},
+};
+
+/// Number of halfmoves without a capture or pawn move after which a draw can be claimed
+pub const FIFTY_MOVE_RULE_HALFMOVES: u32 = 100;
+/// Number of halfmoves without a capture or pawn move after which the game is drawn automatically
+pub const SEVENTY_FIVE_MOVE_RULE_HALFMOVES: u32 = 150;
+
+/// Everything that makes two positions the same for the repetition rules:
+/// the pieces, the side to move, the castling rights and the en passant rights
+#[derive(Clone, PartialEq)]
+pub struct PositionKey {
+    board: Board,
+    turn: Color,
+    castling_status: CastlingStatus,
+    en_passant_square: Option<Square>,
+}
+
+impl PositionKey {
+    pub fn new(board: &Board, board_data: &AdditionalBoardData, turn: Color) -> Self {
+        Self {
+            board: *board,
+            turn,
+            castling_status: board_data.castling_status,
+            en_passant_square: effective_en_passant_square(board, board_data, turn),
+        }
+    }
+
+    /// The pieces and the side to move, without the castling and en passant rights
+    pub fn board_and_turn(&self) -> (Board, Color) {
+        (self.board, self.turn)
+    }
+}
+
+/// The en passant square only counts as a difference between positions if a pawn can
+/// actually take en passant
+fn effective_en_passant_square(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    turn: Color,
+) -> Option<Square> {
+    let en_passant_square = board_data.en_passant_square?;
+    // the pawns that could take stand beside the pawn that moved, one rank behind the square
+    let behind = -turn.pawn_direction();
+    let can_take = [-1, 1]
+        .into_iter()
+        .filter_map(|files| en_passant_square.offset(files, behind))
+        .any(|pawn_square| {
+            board
+                .piece_at(pawn_square)
+                .is_some_and(|p| p.piece_type == PieceType::Pawn && p.color == turn)
+                && generate_piece_map(board, board_data, turn, pawn_square)
+                    .contains(&en_passant_square)
+        });
+    if can_take {
+        Some(en_passant_square)
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if neither side can possibly checkmate: king against king, king and a single
+/// minor piece against king, or only bishops that all stand on squares of the same color
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let mut minor_pieces = 0;
+    let mut bishop_square_colors = Vec::new();
+    for square in Square::all() {
+        let Some(piece) = board.piece_at(square) else {
+            continue;
+        };
+        match piece.piece_type {
+            PieceType::King => {}
+            PieceType::Knight => minor_pieces += 1,
+            PieceType::Bishop => {
+                minor_pieces += 1;
+                bishop_square_colors.push(square.is_dark());
+            }
+            PieceType::Pawn | PieceType::Rook | PieceType::Queen => return false,
+        }
+    }
+    minor_pieces <= 1
+        || (bishop_square_colors.len() == minor_pieces
+            && bishop_square_colors
+                .iter()
+                .all(|c| *c == bishop_square_colors[0]))
+}
+
+/// Returns `false` if the side can not checkmate by any sequence of legal moves, which turns
+/// a loss on time into a draw: a lone king, or king and a single minor piece or only bishops
+/// on squares of the same color against a lone king
+pub fn can_checkmate(board: &Board, color: Color) -> bool {
+    let king = board.pieces_of(PieceType::King, color);
+    let pieces = board.color(color) & !king;
+    if pieces == 0 {
+        return false;
+    }
+    // with other pieces the opponent could block its own king in, so even a single
+    // minor piece can mate
+    if board.color(color.opposite()).count_ones() > 1 {
+        return true;
+    }
+    let bishops = board.pieces_of(PieceType::Bishop, color);
+    let minor_pieces = board.pieces_of(PieceType::Knight, color) | bishops;
+    if pieces == bishops {
+        let mut square_colors = squares(bishops).map(Square::is_dark);
+        let first = square_colors.next();
+        return square_colors.any(|dark| Some(dark) != first);
+    }
+    pieces != minor_pieces || minor_pieces.count_ones() > 1
+}
@@ -0,0 +1,4 @@
+pub mod background;
+pub mod eval;
+pub mod external;
+pub mod search;
@@ -0,0 +1,88 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+};
+
+use crate::{
+    board::{Board, Color},
+    engine::search::{Position, SearchInfo, SearchLimits, game_history, search},
+    move_validation::movement::PlainMove,
+    state::GameState,
+};
+
+/// A search running on its own thread, so the caller never blocks while it thinks
+pub struct BackgroundSearch {
+    stop: Arc<AtomicBool>,
+    receiver: Receiver<Option<PlainMove>>,
+    /// What the search found at the last completed depth
+    info: Arc<Mutex<Option<SearchInfo>>>,
+}
+
+impl BackgroundSearch {
+    /// Starts searching the current position of the game
+    pub fn for_state(state: &GameState, limits: SearchLimits) -> Self {
+        Self::start(
+            Position::from_state(state),
+            game_history(state),
+            limits,
+            |_| {},
+        )
+    }
+
+    /// Starts searching the position, `on_info` is called on the search thread
+    /// after every completed depth
+    pub fn start(
+        position: Position,
+        history: Vec<(Board, Color)>,
+        limits: SearchLimits,
+        mut on_info: impl FnMut(&SearchInfo) + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let info = Arc::new(Mutex::new(None));
+        let thread_info = info.clone();
+        let (sender, receiver) = mpsc::channel();
+        let thread_stop = stop.clone();
+        thread::spawn(move || {
+            let best_move = search(&position, &history, limits, &thread_stop, |info| {
+                on_info(info);
+                *thread_info.lock().unwrap() = Some(info.clone());
+            });
+            // the receiver is gone if the search was abandoned
+            let _ = sender.send(best_move);
+        });
+        Self {
+            stop,
+            receiver,
+            info,
+        }
+    }
+
+    /// Asks the search to finish as soon as possible, it still reports its best move
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// What the search found at the last completed depth, if it completed one yet
+    pub fn latest_info(&self) -> Option<SearchInfo> {
+        self.info.lock().unwrap().clone()
+    }
+
+    /// Returns the result once the search finished, `Some(None)` if there was no legal move
+    pub fn try_result(&self) -> Option<Option<PlainMove>> {
+        match self.receiver.try_recv() {
+            Ok(best_move) => Some(best_move),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(None),
+        }
+    }
+}
+
+impl Drop for BackgroundSearch {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
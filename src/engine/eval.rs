@@ -0,0 +1,151 @@
+use crate::{
+    bitboard::squares,
+    board::{Board, Color, PIECE_TYPES, PieceType},
+};
+
+/// Material values in centipawns
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 20000,
+    }
+}
+
+/// Evaluates the position from the point of view of `turn` in centipawns,
+/// using material and piece-square tables
+pub fn evaluate(board: &Board, turn: Color) -> i32 {
+    let endgame = is_endgame(board);
+    let mut score = 0;
+    for color in [Color::White, Color::Black] {
+        let sign = if color == turn { 1 } else { -1 };
+        for piece_type in PIECE_TYPES {
+            for square in squares(board.pieces_of(piece_type, color)) {
+                // the tables are written from white's point of view with the 8th rank first,
+                // which is the order of `Square`; for black the ranks are mirrored
+                let table_index = if color == Color::White {
+                    square.index()
+                } else {
+                    square.mirrored().index()
+                };
+                score += sign
+                    * (piece_value(piece_type)
+                        + piece_square_value(piece_type, table_index, endgame));
+            }
+        }
+    }
+    score
+}
+
+/// The endgame starts when both sides have no queens, or every side with a queen
+/// has at most one minor piece besides it
+fn is_endgame(board: &Board) -> bool {
+    [Color::White, Color::Black].into_iter().all(|color| {
+        let queens = board.pieces_of(PieceType::Queen, color).count_ones();
+        let rooks = board.pieces_of(PieceType::Rook, color).count_ones();
+        let minors = (board.pieces_of(PieceType::Knight, color)
+            | board.pieces_of(PieceType::Bishop, color))
+        .count_ones();
+        queens == 0 || (rooks == 0 && minors <= 1)
+    })
+}
+
+fn piece_square_value(piece_type: PieceType, index: usize, endgame: bool) -> i32 {
+    match piece_type {
+        PieceType::Pawn => PAWN_TABLE[index],
+        PieceType::Knight => KNIGHT_TABLE[index],
+        PieceType::Bishop => BISHOP_TABLE[index],
+        PieceType::Rook => ROOK_TABLE[index],
+        PieceType::Queen => QUEEN_TABLE[index],
+        PieceType::King if endgame => KING_ENDGAME_TABLE[index],
+        PieceType::King => KING_MIDDLEGAME_TABLE[index],
+    }
+}
+
+// Piece-square tables of the Simplified Evaluation Function by Tomasz Michniewski
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
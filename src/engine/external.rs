@@ -0,0 +1,290 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    engine::search::{MATE_SCORE, SearchInfo},
+    move_validation::movement::PlainMove,
+    state::GameState,
+};
+
+/// How often and how long apart a dropped engine is checked for having quit
+const QUIT_POLLS: u32 = 20;
+const QUIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+pub enum ExternalEngineError {
+    /// The executable could not be started
+    Spawn(io::Error),
+    /// The engine exited or closed its input
+    Disconnected,
+}
+
+impl Display for ExternalEngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExternalEngineError::Spawn(err) => write!(f, "could not start engine: {}", err),
+            ExternalEngineError::Disconnected => write!(f, "the engine stopped responding"),
+        }
+    }
+}
+
+impl std::error::Error for ExternalEngineError {}
+
+/// What an external engine sent, one line at a time
+enum EngineOutput {
+    Name(String),
+    ReadyOk,
+    Info(SearchInfo),
+    /// `None` if the engine had no move to play
+    BestMove(Option<PlainMove>),
+}
+
+/// A UCI engine executable running as a child process. Its output is read on a
+/// separate thread, so none of the methods block while the engine thinks.
+pub struct ExternalEngine {
+    child: Child,
+    input: ChildStdin,
+    receiver: Receiver<EngineOutput>,
+    name: Option<String>,
+    ready: bool,
+    searching: bool,
+    /// `bestmove` answers of stopped searches that are still to come and must be ignored
+    stopped_searches: usize,
+    /// The `position` command of the current search
+    search_position: Option<String>,
+    latest_info: Option<SearchInfo>,
+}
+
+impl ExternalEngine {
+    /// Starts the executable and begins the handshake, see `is_ready`
+    pub fn spawn(path: &str) -> Result<Self, ExternalEngineError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(ExternalEngineError::Spawn)?;
+        let input = child
+            .stdin
+            .take()
+            .ok_or(ExternalEngineError::Disconnected)?;
+        let output = child
+            .stdout
+            .take()
+            .ok_or(ExternalEngineError::Disconnected)?;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(message) = parse_output(&line)
+                    && sender.send(message).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            child,
+            input,
+            receiver,
+            name: None,
+            ready: false,
+            searching: false,
+            stopped_searches: 0,
+            search_position: None,
+            latest_info: None,
+        };
+        // the engine answers `isready` only after it finished the `uci` handshake
+        engine.send("uci")?;
+        engine.send("isready")?;
+        Ok(engine)
+    }
+
+    /// The name the engine reported in the handshake
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// `true` once the engine finished the handshake and accepts positions
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// `true` if the engine is searching the current position of the game
+    pub fn is_searching_position(&self, state: &GameState) -> bool {
+        self.searching && self.search_position.as_deref() == Some(&position_command(state))
+    }
+
+    /// What the engine reported last about its current search
+    pub fn latest_info(&self) -> Option<&SearchInfo> {
+        self.latest_info.as_ref()
+    }
+
+    /// Searches the current position of the game for `time`, or until `stop` without a time
+    pub fn start_search(
+        &mut self,
+        state: &GameState,
+        time: Option<Duration>,
+    ) -> Result<(), ExternalEngineError> {
+        self.stop()?;
+        let position = position_command(state);
+        self.send(&position)?;
+        match time {
+            Some(time) => self.send(&format!("go movetime {}", time.as_millis()))?,
+            None => self.send("go infinite")?,
+        }
+        self.searching = true;
+        self.search_position = Some(position);
+        self.latest_info = None;
+        Ok(())
+    }
+
+    /// Stops the current search, its best move is ignored
+    pub fn stop(&mut self) -> Result<(), ExternalEngineError> {
+        if self.searching {
+            self.searching = false;
+            self.search_position = None;
+            self.stopped_searches += 1;
+            self.send("stop")?;
+        }
+        Ok(())
+    }
+
+    /// Handles everything the engine sent since the last call. Returns the best move
+    /// once the current search finished, `Some(None)` if the engine found no move.
+    pub fn poll(&mut self) -> Result<Option<Option<PlainMove>>, ExternalEngineError> {
+        loop {
+            let message = match self.receiver.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => return Err(ExternalEngineError::Disconnected),
+            };
+            match message {
+                EngineOutput::Name(name) => self.name = Some(name),
+                EngineOutput::ReadyOk => self.ready = true,
+                EngineOutput::Info(info) => {
+                    if self.searching && self.stopped_searches == 0 {
+                        self.latest_info = Some(info);
+                    }
+                }
+                EngineOutput::BestMove(best_move) => {
+                    if self.stopped_searches > 0 {
+                        self.stopped_searches -= 1;
+                    } else if self.searching {
+                        self.searching = false;
+                        self.search_position = None;
+                        return Ok(Some(best_move));
+                    }
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), ExternalEngineError> {
+        writeln!(self.input, "{}", command)
+            .and_then(|_| self.input.flush())
+            .map_err(|_| ExternalEngineError::Disconnected)
+    }
+}
+
+impl Drop for ExternalEngine {
+    /// Asks the engine to quit and kills it if it has not exited shortly after
+    fn drop(&mut self) {
+        if self.send("quit").is_ok() {
+            for _ in 0..QUIT_POLLS {
+                if let Ok(Some(_)) = self.child.try_wait() {
+                    return;
+                }
+                thread::sleep(QUIT_POLL_INTERVAL);
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The `position` command for the current position of the game: its starting position
+/// followed by the moves played so far in long algebraic notation
+pub fn position_command(state: &GameState) -> String {
+    let mut command = format!("position fen {}", state.initial_fen);
+    if !state.move_history.is_empty() {
+        command.push_str(" moves");
+        for record in &state.move_history {
+            let m = PlainMove {
+                start: record.start,
+                destination: record.destination,
+                promotion: record.promotion,
+            };
+            command.push(' ');
+            command.push_str(&m.to_long_algebraic());
+        }
+    }
+    command
+}
+
+fn parse_output(line: &str) -> Option<EngineOutput> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    match tokens.as_slice() {
+        ["id", "name", name @ ..] => Some(EngineOutput::Name(name.join(" "))),
+        ["readyok", ..] => Some(EngineOutput::ReadyOk),
+        ["bestmove", best_move, ..] => Some(EngineOutput::BestMove(
+            PlainMove::from_long_algebraic(best_move),
+        )),
+        ["info", fields @ ..] => parse_info(fields).map(EngineOutput::Info),
+        _ => None,
+    }
+}
+
+/// Reads an `info` line with a score, lines with only other fields like `currmove` are skipped
+fn parse_info(fields: &[&str]) -> Option<SearchInfo> {
+    let mut info = SearchInfo {
+        depth: 0,
+        score: 0,
+        nodes: 0,
+        time: Duration::ZERO,
+        pv: Vec::new(),
+    };
+    let mut has_score = false;
+    let mut tokens = fields.iter();
+    while let Some(token) = tokens.next() {
+        match *token {
+            "depth" => info.depth = tokens.next()?.parse().ok()?,
+            "nodes" => info.nodes = tokens.next()?.parse().ok()?,
+            "time" => info.time = Duration::from_millis(tokens.next()?.parse().ok()?),
+            "score" => {
+                let kind = tokens.next()?;
+                let value: i32 = tokens.next()?.parse().ok()?;
+                info.score = match *kind {
+                    "cp" => value,
+                    // `mate 3` is a mate in 5 plies, counted the same way as our own search
+                    "mate" if value > 0 => MATE_SCORE - (2 * value - 1),
+                    "mate" => -MATE_SCORE + 2 * -value,
+                    _ => return None,
+                };
+                has_score = true;
+            }
+            "pv" => {
+                info.pv = tokens
+                    .by_ref()
+                    .map_while(|m| PlainMove::from_long_algebraic(m))
+                    .collect();
+            }
+            // a string runs until the end of the line
+            "string" => break,
+            _ => {}
+        }
+    }
+    has_score.then_some(info)
+}
@@ -0,0 +1,373 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::{Board, BoardExt, Color, PieceType},
+    draw_rules::FIFTY_MOVE_RULE_HALFMOVES,
+    engine::eval::{evaluate, piece_value},
+    move_validation::{
+        movement::PlainMove,
+        validator::{generate_legal_moves, is_in_check, play_move},
+    },
+    state::{AdditionalBoardData, GameState},
+};
+
+/// The score of being checkmated right now, mates further away score closer to zero
+pub const MATE_SCORE: i32 = 100_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+const MAX_PLY: usize = 128;
+/// How many nodes are searched between checks of the clock and the stop flag
+const CHECK_INTERVAL: u64 = 2048;
+/// The history score at which the whole history table is halved, which keeps it
+/// below the scores of killer moves and older cutoffs from outweighing recent ones
+const MAX_HISTORY: i32 = 1_000_000;
+
+/// A position to search, with everything needed to generate its moves
+#[derive(Clone, Copy)]
+pub struct Position {
+    pub board: Board,
+    pub board_data: AdditionalBoardData,
+    pub turn: Color,
+}
+
+impl Position {
+    pub fn from_state(state: &GameState) -> Self {
+        Self {
+            board: state.board,
+            board_data: state.additional_board_data,
+            turn: state.turn,
+        }
+    }
+
+    /// Returns the position after the move. Does not validate the move.
+    pub fn after(&self, m: PlainMove) -> Self {
+        let mut position = *self;
+        play_move(
+            &mut position.board,
+            &mut position.board_data,
+            m.start,
+            m.destination,
+            m.promotion,
+        );
+        position.turn = self.turn.opposite();
+        position
+    }
+
+    fn key(&self) -> (Board, Color) {
+        (self.board, self.turn)
+    }
+}
+
+/// The positions of the game before the current one, for the repetition detection of `search`
+pub fn game_history(state: &GameState) -> Vec<(Board, Color)> {
+    let mut history: Vec<(Board, Color)> = state
+        .position_history
+        .iter()
+        .map(|position| position.board_and_turn())
+        .collect();
+    // the last entry of the history is the current position itself
+    history.pop();
+    history
+}
+
+/// When to stop searching. Without any limit the search only ends when it is stopped.
+#[derive(Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+/// What the search found after completing a depth
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    /// Centipawns from the point of view of the side to move, see `MATE_SCORE`
+    pub score: i32,
+    pub nodes: u64,
+    pub time: Duration,
+    /// The principal variation, the best line for both sides
+    pub pv: Vec<PlainMove>,
+}
+
+impl SearchInfo {
+    /// Moves until mate, negative if the side to move gets mated, `None` if no mate was found
+    pub fn mate_in(&self) -> Option<i32> {
+        let distance = MATE_SCORE - self.score.abs();
+        if distance > MAX_PLY as i32 {
+            return None;
+        }
+        let moves = (distance + 1) / 2;
+        Some(if self.score > 0 { moves } else { -moves })
+    }
+}
+
+/// Searches for the best move of the position with iterative deepening alpha-beta.
+/// `history` holds the positions of the game before this one, to detect repetitions.
+/// `on_info` is called after every completed depth. Returns `None` if there is no legal move.
+pub fn search(
+    position: &Position,
+    history: &[(Board, Color)],
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    mut on_info: impl FnMut(&SearchInfo),
+) -> Option<PlainMove> {
+    let root_moves = generate_legal_moves(&position.board, &position.board_data, position.turn);
+    let mut best_move = *root_moves.first()?;
+    let start_time = Instant::now();
+    let mut searcher = Searcher {
+        stop,
+        deadline: limits.time.map(|time| start_time + time),
+        nodes: 0,
+        stopped: false,
+        path: history.to_vec(),
+        killers: [[None; 2]; MAX_PLY],
+        history: Box::new([[[0; 64]; 64]; 2]),
+        pv: vec![Vec::new(); MAX_PLY + 1],
+        previous_pv: Vec::new(),
+    };
+    let max_depth = limits.depth.unwrap_or(MAX_PLY as u32 - 1).max(1);
+    for depth in 1..=max_depth {
+        let score = searcher.negamax(position, depth, 0, -INFINITY, INFINITY);
+        // an unfinished iteration is only trusted as far as its first move, which was
+        // the best move of the previous iteration and therefore searched first
+        if searcher.stopped {
+            if let Some(m) = searcher.pv[0].first().filter(|_| depth > 1) {
+                best_move = *m;
+            }
+            break;
+        }
+        if let Some(m) = searcher.pv[0].first() {
+            best_move = *m;
+        }
+        searcher.previous_pv = searcher.pv[0].clone();
+        on_info(&SearchInfo {
+            depth,
+            score,
+            nodes: searcher.nodes,
+            time: start_time.elapsed(),
+            pv: searcher.pv[0].clone(),
+        });
+        // no need to look deeper once a mate is certain, or with a single legal move
+        if score.abs() > MATE_SCORE - MAX_PLY as i32 || root_moves.len() == 1 {
+            break;
+        }
+    }
+    Some(best_move)
+}
+
+struct Searcher<'a> {
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+    /// Positions of the game and the current line, to detect repetitions
+    path: Vec<(Board, Color)>,
+    /// Two quiet moves per ply that recently caused a beta cutoff
+    killers: [[Option<PlainMove>; 2]; MAX_PLY],
+    /// How often a quiet move caused a cutoff, weighted by depth, per color, start and destination
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// Triangular table of principal variations, `pv[ply]` is the best line from that ply on
+    pv: Vec<Vec<PlainMove>>,
+    previous_pv: Vec<PlainMove>,
+}
+
+impl Searcher<'_> {
+    fn negamax(
+        &mut self,
+        position: &Position,
+        mut depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.pv[ply].clear();
+        if ply > 0 && self.is_draw(position) {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return evaluate(&position.board, position.turn);
+        }
+        let in_check = is_in_check(&position.board, position.turn);
+        // look one move deeper when in check, so mates and escapes are not cut off
+        if in_check {
+            depth += 1;
+        }
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+        if self.count_node() {
+            return 0;
+        }
+
+        let mut moves = generate_legal_moves(&position.board, &position.board_data, position.turn);
+        if moves.is_empty() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        self.order_moves(position, &mut moves, ply);
+
+        let mut best_score = -INFINITY;
+        for m in moves {
+            let child = position.after(m);
+            self.path.push(position.key());
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha);
+            self.path.pop();
+            if self.stopped {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+                let (current, deeper) = self.pv.split_at_mut(ply + 1);
+                current[ply].clear();
+                current[ply].push(m);
+                current[ply].extend_from_slice(&deeper[0]);
+            }
+            if alpha >= beta {
+                if !is_capture(&position.board, m) && m.promotion.is_none() {
+                    self.store_killer(m, ply);
+                    self.store_history(position.turn, m, depth);
+                }
+                break;
+            }
+        }
+        best_score
+    }
+
+    /// Searches only captures and promotions until the position is quiet, so the
+    /// evaluation is never taken in the middle of an exchange. In check every evasion
+    /// is searched, as the side to move cannot choose to stand pat.
+    fn quiescence(&mut self, position: &Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if self.count_node() {
+            return 0;
+        }
+        let in_check = is_in_check(&position.board, position.turn);
+        let legal_moves =
+            generate_legal_moves(&position.board, &position.board_data, position.turn);
+        if in_check && legal_moves.is_empty() {
+            return -MATE_SCORE + ply as i32;
+        }
+        let stand_pat = evaluate(&position.board, position.turn);
+        if ply >= MAX_PLY - 1 {
+            return stand_pat;
+        }
+        if !in_check {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+
+        let mut moves: Vec<PlainMove> = legal_moves
+            .into_iter()
+            .filter(|m| {
+                in_check || is_capture(&position.board, *m) || m.promotion == Some(PieceType::Queen)
+            })
+            .collect();
+        moves.sort_by_cached_key(|m| -mvv_lva(&position.board, *m));
+
+        for m in moves {
+            let score = -self.quiescence(&position.after(m), ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Counts the node and checks from time to time whether the search has to stop.
+    /// Returns `true` if it has.
+    fn count_node(&mut self) -> bool {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL)
+            && (self.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    /// Positions repeated once within the game or the searched line count as a draw,
+    /// as the side that can repeat them can do so again
+    fn is_draw(&self, position: &Position) -> bool {
+        position.board_data.halfmove_clock >= FIFTY_MOVE_RULE_HALFMOVES
+            || self.path.contains(&position.key())
+    }
+
+    /// Orders the moves so that the ones most likely to be best are searched first:
+    /// the move of the previous principal variation, captures by most valuable victim
+    /// and least valuable attacker, promotions, killer moves and then by history
+    fn order_moves(&self, position: &Position, moves: &mut [PlainMove], ply: usize) {
+        let pv_move = self.previous_pv.get(ply).copied();
+        let killers = self.killers[ply];
+        let history = &self.history[position.turn as usize];
+        moves.sort_by_cached_key(|m| {
+            let score = if Some(*m) == pv_move {
+                3_000_000
+            } else if is_capture(&position.board, *m) {
+                2_000_000 + mvv_lva(&position.board, *m)
+            } else if let Some(promotion) = m.promotion {
+                1_900_000 + piece_value(promotion)
+            } else if killers[0] == Some(*m) {
+                1_800_000
+            } else if killers[1] == Some(*m) {
+                1_700_000
+            } else {
+                history[m.start.index()][m.destination.index()]
+            };
+            -score
+        });
+    }
+
+    fn store_history(&mut self, turn: Color, m: PlainMove, depth: u32) {
+        let entry = &mut self.history[turn as usize][m.start.index()][m.destination.index()];
+        *entry += (depth * depth) as i32;
+        if *entry > MAX_HISTORY {
+            for score in self.history.iter_mut().flatten().flatten() {
+                *score /= 2;
+            }
+        }
+    }
+
+    fn store_killer(&mut self, m: PlainMove, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+    }
+}
+
+/// A pawn moving diagonally always captures, either normally or en passant
+fn is_capture(board: &Board, m: PlainMove) -> bool {
+    board.piece_at(m.destination).is_some()
+        || (board
+            .piece_at(m.start)
+            .is_some_and(|p| p.piece_type == PieceType::Pawn)
+            && m.start.file() != m.destination.file())
+}
+
+/// Most valuable victim, least valuable attacker
+fn mvv_lva(board: &Board, m: PlainMove) -> i32 {
+    let victim = board
+        .piece_at(m.destination)
+        .map_or(PieceType::Pawn, |p| p.piece_type);
+    let attacker = board
+        .piece_at(m.start)
+        .map_or(PieceType::Pawn, |p| p.piece_type);
+    piece_value(victim) * 10 - piece_value(attacker)
+}
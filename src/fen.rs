@@ -0,0 +1,478 @@
+use std::fmt::Display;
+
+use crate::{
+    BOARD_SQUARES,
+    board::{Board, BoardExt, Color, Piece, PieceType},
+    move_validation::validator::is_in_check,
+    square::{File, Rank, Square},
+    state::{AdditionalBoardData, CastlingStatus},
+};
+
+pub const DEFAULT_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    /// A FEN needs the piece placement, side to move, castling and en passant fields,
+    /// optionally followed by the halfmove clock and fullmove number
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    /// The rank (counted from 8 down to 1) does not describe exactly 8 squares
+    WrongRankLength {
+        rank: Rank,
+        squares: usize,
+    },
+    /// Empty squares are counted by a single digit, so two digits in a row are not allowed
+    ConsecutiveDigits(Rank),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastlingRights(String),
+    /// The castling right needs the king and the rook on their starting squares
+    CastlingWithoutKingOrRook(char),
+    InvalidEnPassantSquare(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// Each side needs exactly one king
+    WrongKingCount {
+        color: Color,
+        count: usize,
+    },
+    PawnOnFirstOrLastRank(Square),
+    /// The side that is not to move is in check, so its king could be captured
+    OpponentInCheck,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 or 6 space separated fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => {
+                write!(
+                    f,
+                    "expected 8 ranks in the piece placement, found {}",
+                    count
+                )
+            }
+            FenError::WrongRankLength { rank, squares } => {
+                write!(
+                    f,
+                    "rank {} describes {} squares instead of 8",
+                    rank, squares
+                )
+            }
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a valid piece", c),
+            FenError::InvalidSideToMove(s) => {
+                write!(f, "side to move must be 'w' or 'b', found '{}'", s)
+            }
+            FenError::ConsecutiveDigits(rank) => {
+                write!(f, "rank {} has two digits in a row", rank)
+            }
+            FenError::InvalidCastlingRights(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::CastlingWithoutKingOrRook(c) => write!(
+                f,
+                "castling right '{}' needs the king and rook on their starting squares",
+                c
+            ),
+            FenError::InvalidEnPassantSquare(s) => write!(f, "invalid en passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+            FenError::WrongKingCount { color, count } => write!(
+                f,
+                "{} has {} kings instead of 1",
+                if *color == Color::White {
+                    "white"
+                } else {
+                    "black"
+                },
+                count
+            ),
+            FenError::PawnOnFirstOrLastRank(square) => {
+                write!(f, "pawn on {} can not exist", square)
+            }
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Parses a FEN string into the board, the side to move and the additional board data
+pub fn parse_fen(fen: &str) -> Result<(Board, Color, AdditionalBoardData), FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() != 4 && fields.len() != 6 {
+        return Err(FenError::WrongFieldCount(fields.len()));
+    }
+
+    let board = parse_piece_placement(fields[0])?;
+
+    let turn = match fields[1] {
+        "w" => Color::White,
+        "b" => Color::Black,
+        other => return Err(FenError::InvalidSideToMove(other.to_string())),
+    };
+    if is_in_check(&board, turn.opposite()) {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    let castling_status = parse_castling_rights(fields[2])?;
+    check_castling_pieces(&board, castling_status)?;
+
+    let en_passant_square = if fields[3] == "-" {
+        None
+    } else {
+        let square = Square::from_algebraic(fields[3])
+            .ok_or_else(|| FenError::InvalidEnPassantSquare(fields[3].to_string()))?;
+        // the square behind a pawn of the opponent that just moved two squares
+        if square.rank() != Rank::SIXTH.relative_to(turn) {
+            return Err(FenError::InvalidEnPassantSquare(fields[3].to_string()));
+        }
+        Some(square)
+    };
+
+    let (halfmove_clock, fullmove_number) = if fields.len() == 6 {
+        let halfmove_clock = fields[4]
+            .parse::<u32>()
+            .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+        let fullmove_number = fields[5]
+            .parse::<u32>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+        (halfmove_clock, fullmove_number)
+    } else {
+        (0, 1)
+    };
+
+    Ok((
+        board,
+        turn,
+        AdditionalBoardData {
+            castling_status,
+            en_passant_square,
+            halfmove_clock,
+            fullmove_number,
+        },
+    ))
+}
+
+fn parse_piece_placement(placement: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != BOARD_SQUARES {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut board = Board::empty();
+    // the placement starts with the eighth rank
+    for (rank, text) in Rank::all().rev().zip(ranks) {
+        // `None` once the text went past the h-file
+        let mut file = Some(File::A);
+        let mut squares = 0;
+        let mut previous_digit = false;
+        for c in text.chars() {
+            if let Some(empty) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                if previous_digit {
+                    return Err(FenError::ConsecutiveDigits(rank));
+                }
+                previous_digit = true;
+                file = file.and_then(|file| file.offset(empty as i8));
+                squares += empty as usize;
+                continue;
+            }
+            previous_digit = false;
+            let piece = piece_from_char(c).ok_or(FenError::InvalidPiece(c))?;
+            if let Some(file) = file {
+                board.set_piece(Square::new(file, rank), Some(piece));
+            }
+            file = file.and_then(|file| file.offset(1));
+            squares += 1;
+        }
+        if squares != BOARD_SQUARES {
+            return Err(FenError::WrongRankLength { rank, squares });
+        }
+    }
+
+    for color in [Color::White, Color::Black] {
+        let count = board.pieces_of(PieceType::King, color).count_ones() as usize;
+        if count != 1 {
+            return Err(FenError::WrongKingCount { color, count });
+        }
+    }
+    for square in Square::all() {
+        if (square.rank() == Rank::FIRST || square.rank() == Rank::EIGHTH)
+            && board
+                .piece_at(square)
+                .is_some_and(|p| p.piece_type == PieceType::Pawn)
+        {
+            return Err(FenError::PawnOnFirstOrLastRank(square));
+        }
+    }
+    Ok(board)
+}
+
+fn parse_castling_rights(castling: &str) -> Result<CastlingStatus, FenError> {
+    let mut status = ((false, false), (false, false));
+    if castling == "-" {
+        return Ok(status);
+    }
+    for c in castling.chars() {
+        let right = match c {
+            'K' => &mut status.0.0,
+            'Q' => &mut status.0.1,
+            'k' => &mut status.1.0,
+            'q' => &mut status.1.1,
+            _ => return Err(FenError::InvalidCastlingRights(castling.to_string())),
+        };
+        if *right {
+            return Err(FenError::InvalidCastlingRights(castling.to_string()));
+        }
+        *right = true;
+    }
+    Ok(status)
+}
+
+/// Every castling right needs the king on the e-file and the rook in the corner of its
+/// home rank, otherwise castling could never be played
+fn check_castling_pieces(board: &Board, castling_status: CastlingStatus) -> Result<(), FenError> {
+    let ((white_kingside, white_queenside), (black_kingside, black_queenside)) = castling_status;
+    let rights = [
+        (white_kingside, 'K', Color::White, File::H),
+        (white_queenside, 'Q', Color::White, File::A),
+        (black_kingside, 'k', Color::Black, File::H),
+        (black_queenside, 'q', Color::Black, File::A),
+    ];
+    for (allowed, c, color, rook_file) in rights {
+        let home_rank = Rank::FIRST.relative_to(color);
+        let has_piece = |file: File, piece_type: PieceType| {
+            board.piece_at(Square::new(file, home_rank)) == Some(Piece::new(piece_type, color))
+        };
+        if allowed
+            && !(has_piece(File::E, PieceType::King) && has_piece(rook_file, PieceType::Rook))
+        {
+            return Err(FenError::CastlingWithoutKingOrRook(c));
+        }
+    }
+    Ok(())
+}
+
+/// Serializes the position into a FEN string
+pub fn to_fen(board: &Board, turn: Color, board_data: &AdditionalBoardData) -> String {
+    let mut placement = String::new();
+    for rank in Rank::all().rev() {
+        if rank != Rank::EIGHTH {
+            placement.push('/');
+        }
+        let mut empty = 0;
+        for file in File::all() {
+            match board.piece_at(Square::new(file, rank)) {
+                Some(piece) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(piece_to_char(&piece));
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+    }
+
+    let ((white_kingside, white_queenside), (black_kingside, black_queenside)) =
+        board_data.castling_status;
+    let mut castling: String = [
+        (white_kingside, 'K'),
+        (white_queenside, 'Q'),
+        (black_kingside, 'k'),
+        (black_queenside, 'q'),
+    ]
+    .iter()
+    .filter(|(allowed, _)| *allowed)
+    .map(|(_, c)| *c)
+    .collect();
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    format!(
+        "{} {} {} {} {} {}",
+        placement,
+        if turn == Color::White { "w" } else { "b" },
+        castling,
+        board_data
+            .en_passant_square
+            .map_or("-".to_string(), |sq| sq.to_string()),
+        board_data.halfmove_clock,
+        board_data.fullmove_number
+    )
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let piece_type = match c.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    Some(Piece::new(piece_type, color))
+}
+
+fn piece_to_char(piece: &Piece) -> char {
+    let c = match piece.piece_type {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+    if piece.color == Color::White {
+        c.to_ascii_uppercase()
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(fen: &str) -> FenError {
+        parse_fen(fen).err().expect("the FEN should be rejected")
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            DEFAULT_POSITION_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+        ] {
+            let (board, turn, board_data) = parse_fen(fen).unwrap();
+            assert_eq!(to_fen(&board, turn, &board_data), fen);
+        }
+    }
+
+    #[test]
+    fn counters_are_optional() {
+        let (_, _, board_data) = parse_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(board_data.halfmove_clock, 0);
+        assert_eq!(board_data.fullmove_number, 1);
+    }
+
+    #[test]
+    fn piece_placement_errors() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::WrongRankCount(7)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2 w - - 0 1"),
+            FenError::WrongRankLength {
+                rank: Rank::FIRST,
+                squares: 7
+            }
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K4 w - - 0 1"),
+            FenError::WrongRankLength {
+                rank: Rank::FIRST,
+                squares: 9
+            }
+        );
+        assert_eq!(
+            error("4k3/44/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::ConsecutiveDigits(Rank::SEVENTH)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4X3 w - - 0 1"),
+            FenError::InvalidPiece('X')
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 1 2"),
+            FenError::WrongFieldCount(7)
+        );
+    }
+
+    #[test]
+    fn impossible_positions() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4KK2 w - - 0 1"),
+            FenError::WrongKingCount {
+                color: Color::White,
+                count: 2
+            }
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::WrongKingCount {
+                color: Color::Black,
+                count: 0
+            }
+        );
+        assert_eq!(
+            error("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::PawnOnFirstOrLastRank(Square::new(File::A, Rank::EIGHTH))
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1"),
+            FenError::OpponentInCheck
+        );
+        assert!(parse_fen("4k3/8/8/8/8/8/4R3/4K3 b - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn side_to_move_and_castling_errors() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            FenError::InvalidSideToMove("x".to_string())
+        );
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R3K2R w KK - 0 1"),
+            FenError::InvalidCastlingRights("KK".to_string())
+        );
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R3K2R w X - 0 1"),
+            FenError::InvalidCastlingRights("X".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            FenError::CastlingWithoutKingOrRook('K')
+        );
+        assert_eq!(
+            error("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+            FenError::CastlingWithoutKingOrRook('k')
+        );
+    }
+
+    #[test]
+    fn en_passant_and_counter_errors() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - e9 0 1"),
+            FenError::InvalidEnPassantSquare("e9".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - e3 0 1"),
+            FenError::InvalidEnPassantSquare("e3".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            FenError::InvalidHalfmoveClock("x".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+            FenError::InvalidFullmoveNumber("0".to_string())
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::Display;
+
+use crate::board::Color;
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    #[default]
+    Ongoing,
+    WhiteWins(WinReason),
+    BlackWins(WinReason),
+    Draw(DrawReason),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WinReason {
+    Checkmate,
+    /// The opponent ran out of time
+    Timeout,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DrawReason {
+    Stalemate,
+    /// Claimed after 50 moves by each side without a capture or pawn move
+    FiftyMoveRule,
+    /// Automatic after 75 moves by each side without a capture or pawn move
+    SeventyFiveMoveRule,
+    /// Claimed when the same position occurred three times
+    ThreefoldRepetition,
+    /// Automatic when the same position occurred five times
+    FivefoldRepetition,
+    InsufficientMaterial,
+    /// A side ran out of time, but the opponent could not have checkmated
+    TimeoutVsInsufficientMaterial,
+}
+
+impl GameResult {
+    pub fn win_for(color: Color, reason: WinReason) -> Self {
+        match color {
+            Color::White => GameResult::WhiteWins(reason),
+            Color::Black => GameResult::BlackWins(reason),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        *self != GameResult::Ongoing
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Ongoing => write!(f, "Game in progress"),
+            GameResult::WhiteWins(reason) => write!(f, "White wins {}", reason),
+            GameResult::BlackWins(reason) => write!(f, "Black wins {}", reason),
+            GameResult::Draw(reason) => write!(f, "Draw {}", reason),
+        }
+    }
+}
+
+impl Display for WinReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WinReason::Checkmate => write!(f, "by checkmate"),
+            WinReason::Timeout => write!(f, "on time"),
+        }
+    }
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "by stalemate"),
+            DrawReason::FiftyMoveRule => write!(f, "by the fifty-move rule"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "by the seventy-five-move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "by threefold repetition"),
+            DrawReason::FivefoldRepetition => write!(f, "by fivefold repetition"),
+            DrawReason::InsufficientMaterial => write!(f, "by insufficient material"),
+            DrawReason::TimeoutVsInsufficientMaterial => {
+                write!(f, "by timeout vs insufficient material")
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! The chess rules, game state, notation and engine without any GUI: positions and
+//! move generation in `board` and `move_validation`, games with their history, results
+//! and clocks in `state`, FEN, SAN and PGN, and the built-in engine with its UCI front end.
+//! The egui app in `main.rs` is built on top of it with the `gui` feature.
+
+pub mod bitboard;
+pub mod board;
+pub mod clock;
+pub mod draw_rules;
+pub mod engine;
+pub mod fen;
+pub mod game_result;
+pub mod move_validation;
+pub mod pgn;
+pub mod replay;
+pub mod san;
+pub mod square;
+pub mod state;
+pub mod uci;
+
+/// The number of squares along each side of the board
+pub const BOARD_SQUARES: usize = 8;
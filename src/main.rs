@@ -1,26 +1,57 @@
-use eframe::egui::{self, Pos2, Rect, Sense, Vec2, ViewportBuilder};
+use std::time::{Duration, Instant};
 
-use crate::{
-    board::{BoardIndex, BoardIndexExt},
+use eframe::egui::{
+    self, Key, KeyboardShortcut, Modifiers, PointerButton, Pos2, Rect, Sense, ViewportBuilder,
+};
+
+use guh_chess::{
+    board::{BoardExt, Color},
+    clock::{TimeControl, TimeControlMode},
+    engine::{
+        background::BackgroundSearch,
+        external::{ExternalEngine, ExternalEngineError},
+        search::SearchLimits,
+    },
+    pgn::{export_pgn, looks_like_pgn, parse_pgn},
+    replay::Replay,
+    square::Square,
     state::GameState,
-    util::{board_size_vec2, viewport_size_vec2},
 };
 
-mod board;
-mod move_validation;
+use crate::{
+    annotations::{Annotation, toggle_annotation},
+    dialogs::{DialogResult, LoadEngineDialog, OpenPgnDialog, SavePgnDialog},
+    positions::{controls_rect, promotion_picker_squares, square_at},
+    theme::{THEMES_FILE, Theme, load_themes},
+    util::{
+        MIN_SQUARE_SIZE, MOVE_LIST_WIDTH, board_size_vec2, square_size_for, viewport_size_vec2,
+    },
+};
+
+mod annotations;
+mod dialogs;
 mod positions;
 mod rendering;
-mod state;
+mod theme;
 mod util;
 
-pub const BOARD_SQUARES: u16 = 8;
-pub const BOARD_SQUARE_SIZE: u16 = 50;
+const SAVE_PGN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+const OPEN_PGN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::O);
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Y);
+
+/// The size of a square when the window opens
+const DEFAULT_SQUARE_SIZE: f32 = 50.0;
+
+/// How long the computer thinks about each move
+const COMPUTER_THINKING_TIME: Duration = Duration::from_secs(1);
 
 fn main() -> eframe::Result {
     let native_options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
-            .with_inner_size(Vec2::splat(1.0))
-            .with_resizable(false),
+            .with_inner_size(viewport_size_vec2(DEFAULT_SQUARE_SIZE))
+            .with_min_inner_size(viewport_size_vec2(MIN_SQUARE_SIZE))
+            .with_resizable(true),
         ..Default::default()
     };
 
@@ -34,46 +65,892 @@ fn main() -> eframe::Result {
 #[derive(Default)]
 struct ChessApp {
     state: GameState,
+    /// Start and destination of a promotion move waiting for the piece to be chosen
+    pending_promotion: Option<(Square, Square)>,
+    /// The size of a square in points, following the size of the window
+    square_size: f32,
+    /// The square of the piece being dragged and where the cursor holds it
+    dragging: Option<(Square, Pos2)>,
+    /// Shown instead of the turn info until the next click on the board
+    message: Option<String>,
+    /// Arrows and circles drawn with the right mouse button, until the next left click
+    annotations: Vec<Annotation>,
+    /// The square a right button drag started on
+    annotation_start: Option<Square>,
+    save_pgn_dialog: Option<SavePgnDialog>,
+    open_pgn_dialog: Option<OpenPgnDialog>,
+    /// Set while stepping through games loaded from PGN instead of playing
+    replay: Option<Replay>,
+    /// An earlier position of the game chosen in the move list, with the number of
+    /// moves played to reach it
+    viewed_position: Option<(usize, GameState)>,
+    /// The move the move list last scrolled to
+    move_list_scrolled_to: Option<usize>,
+    /// Text field for entering moves in algebraic notation
+    move_input: String,
+    /// The side played by the computer, `None` when two people play
+    computer: Option<Color>,
+    /// The search for the computer's move, while it is thinking
+    computer_search: Option<BackgroundSearch>,
+    /// A UCI engine that plays instead of the built-in one and can analyse positions
+    external_engine: Option<ExternalEngine>,
+    /// Set while the external engine searches for the computer's move
+    external_engine_playing: bool,
+    /// Lets the external engine analyse the displayed position continuously
+    analysis: bool,
+    load_engine_dialog: Option<LoadEngineDialog>,
+    /// The time control of new games, `None` to play without clocks
+    time_control: Option<TimeControl>,
+    /// The side shown at the bottom of the board
+    bottom_side: Color,
+    /// Turns the board after every move so the side to move is at the bottom
+    auto_flip: bool,
+    /// The built-in themes followed by the custom ones
+    themes: Vec<Theme>,
+    theme_index: usize,
+    /// Draws a-h and 1-8 on the edge of the board
+    show_coordinates: bool,
 }
 
 impl ChessApp {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         egui_extras::install_image_loaders(&cc.egui_ctx);
-        let state = state::GameState::new_with_default_position();
-        Self { state: state }
+        let state = GameState::new_with_default_position();
+        let (themes, theme_error) = load_themes();
+        Self {
+            state,
+            pending_promotion: None,
+            square_size: DEFAULT_SQUARE_SIZE,
+            dragging: None,
+            message: theme_error,
+            annotations: Vec::new(),
+            annotation_start: None,
+            save_pgn_dialog: None,
+            open_pgn_dialog: None,
+            replay: None,
+            viewed_position: None,
+            move_list_scrolled_to: None,
+            move_input: String::new(),
+            computer: None,
+            computer_search: None,
+            external_engine: None,
+            external_engine_playing: false,
+            analysis: false,
+            load_engine_dialog: None,
+            time_control: None,
+            bottom_side: Color::White,
+            auto_flip: false,
+            themes,
+            theme_index: 0,
+            show_coordinates: true,
+        }
+    }
+
+    /// The game being replayed if there is one, otherwise the position chosen in the
+    /// move list or the game being played
+    pub fn displayed_state(&self) -> &GameState {
+        match (&self.replay, &self.viewed_position) {
+            (Some(replay), _) => replay.current(),
+            (None, Some((_, state))) => state,
+            (None, None) => &self.state,
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme_index]
+    }
+
+    /// The side at the bottom of the board as it is drawn right now
+    pub fn orientation(&self) -> Color {
+        if self.auto_flip {
+            self.displayed_state().turn
+        } else {
+            self.bottom_side
+        }
+    }
+
+    fn flip_board(&mut self) {
+        self.bottom_side = self.orientation().opposite();
+        self.auto_flip = false;
+    }
+
+    /// `false` while replaying or looking at an earlier position, after the game ended
+    /// and while the computer is to move
+    fn can_move_pieces(&self) -> bool {
+        self.replay.is_none()
+            && self.viewed_position.is_none()
+            && !self.state.result.is_over()
+            && !self.is_computer_turn()
     }
 
     pub fn on_click(&mut self, pos: Pos2) {
-        let index = BoardIndex::from_screen_click(pos);
+        self.message = None;
+        self.annotations.clear();
+        // a click on an earlier position returns to the game
+        if self.replay.is_none() && self.viewed_position.take().is_some() {
+            return;
+        }
+        if !self.can_move_pieces() {
+            return;
+        }
+        let Some(square) = square_at(pos, self.orientation(), self.square_size) else {
+            return;
+        };
+        // a click while the promotion picker is open either chooses a piece or cancels the move
+        if let Some((start, destination)) = self.pending_promotion.take() {
+            let choice = promotion_picker_squares(destination, self.state.turn)
+                .into_iter()
+                .find(|(picker_square, _)| *picker_square == square);
+            if let Some((_, piece_type)) = choice {
+                self.state.move_piece(start, destination, Some(piece_type));
+            }
+            return;
+        }
         // either select square or move piece
         if self.state.selected_square.is_none() {
-            self.state.selected_square = Some(index);
+            self.state.selected_square = Some(square);
         } else {
-            let piece_moved = self
-                .state
-                .move_piece(self.state.selected_square.unwrap(), index);
+            let start = self.state.selected_square.unwrap();
+            self.state.selected_square = None;
+            if self.state.is_promotion(start, square) {
+                self.pending_promotion = Some((start, square));
+                return;
+            }
+            self.state.move_piece(start, square, None);
+        }
+    }
+
+    /// Picks up a piece of the side to move, which also selects it so its legal
+    /// destinations are highlighted
+    fn on_drag_start(&mut self, pos: Pos2) {
+        self.message = None;
+        if !self.can_move_pieces() || self.pending_promotion.is_some() {
+            return;
+        }
+        let Some(square) = square_at(pos, self.orientation(), self.square_size) else {
+            return;
+        };
+        if self
+            .state
+            .board
+            .piece_at(square)
+            .is_some_and(|piece| piece.color == self.state.turn)
+        {
+            self.state.selected_square = Some(square);
+            self.dragging = Some((square, pos));
+        }
+    }
+
+    /// Moves the dragged piece to the square it is dropped on. Dropped anywhere else it
+    /// snaps back, dropped on its own square it stays selected for a click on the destination.
+    fn on_drag_end(&mut self, pos: Pos2) {
+        let Some((start, _)) = self.dragging.take() else {
+            return;
+        };
+        let destination = square_at(pos, self.orientation(), self.square_size);
+        if destination == Some(start) {
+            return;
+        }
+        self.state.selected_square = None;
+        let Some(destination) = destination else {
+            return;
+        };
+        if self.state.is_promotion(start, destination) {
+            self.pending_promotion = Some((start, destination));
+            return;
+        }
+        self.state.move_piece(start, destination, None);
+    }
+
+    /// Remembers the square a right button drag starts on
+    fn on_annotation_start(&mut self, pos: Pos2) {
+        self.annotation_start = square_at(pos, self.orientation(), self.square_size);
+    }
+
+    /// Draws an arrow to the square the right button is released on, or a circle if it
+    /// is the square the drag started on. Drawing the same annotation again removes it.
+    fn on_annotation_end(&mut self, pos: Pos2) {
+        let Some(start) = self.annotation_start.take() else {
+            return;
+        };
+        if let Some(end) = square_at(pos, self.orientation(), self.square_size) {
+            toggle_annotation(&mut self.annotations, Annotation::between(start, end));
+        }
+    }
+
+    /// Copying puts the FEN of the displayed position on the clipboard,
+    /// pasting a FEN replaces the game with a new one starting at that position
+    /// and pasting PGN starts replaying the games in it
+    fn handle_clipboard(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let events = ctx.input(|i| i.events.clone());
+        for event in events {
+            match event {
+                egui::Event::Copy => {
+                    ctx.copy_text(self.displayed_state().to_fen());
+                    self.message = Some("Copied FEN".to_string());
+                }
+                egui::Event::Paste(text) => match GameState::from_fen(&text) {
+                    Ok(state) => self.start_game(state),
+                    Err(_) if looks_like_pgn(&text) => self.start_replay(&text),
+                    Err(fen_err) => self.message = Some(format!("Invalid FEN: {}", fen_err)),
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Replaces the game, with clocks if a time control is set
+    fn start_game(&mut self, mut state: GameState) {
+        if let Some(time_control) = self.time_control {
+            state.start_clock(time_control, Instant::now());
+        }
+        self.state = state;
+        self.replay = None;
+        self.viewed_position = None;
+        self.annotations.clear();
+        self.pending_promotion = None;
+        self.message = None;
+        self.stop_computer();
+    }
+
+    /// Abandons the computer's search, after the game changed under it
+    fn stop_computer(&mut self) {
+        self.computer_search = None;
+        self.external_engine_playing = false;
+        if let Some(engine) = &mut self.external_engine {
+            let _ = engine.stop();
+        }
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        self.computer == Some(self.state.turn)
+    }
+
+    /// Lets the computer play the side to move if it is its turn. The search runs in the
+    /// background and is polled on every frame until it reports its move.
+    fn play_computer_move(&mut self, ctx: &egui::Context) {
+        if self.external_engine.is_some() {
+            self.drive_external_engine(ctx);
+            return;
+        }
+        if self.replay.is_some() || self.state.result.is_over() || !self.is_computer_turn() {
+            self.computer_search = None;
+            return;
+        }
+        let Some(search) = &self.computer_search else {
+            self.computer_search = Some(BackgroundSearch::for_state(
+                &self.state,
+                SearchLimits {
+                    depth: None,
+                    time: Some(self.computer_thinking_time()),
+                },
+            ));
+            ctx.request_repaint_after(Duration::from_millis(50));
+            return;
+        };
+        match search.try_result() {
+            Some(best_move) => {
+                self.computer_search = None;
+                if let Some(m) = best_move {
+                    self.state.move_piece(m.start, m.destination, m.promotion);
+                    self.state.selected_square = None;
+                    self.pending_promotion = None;
+                }
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(50)),
+        }
+    }
+
+    /// Lets the external engine play the computer's moves and, on the other moves,
+    /// analyse the displayed position if analysis is switched on
+    fn drive_external_engine(&mut self, ctx: &egui::Context) {
+        // taken out while in use, so the rest of the app can be borrowed alongside it
+        let Some(mut engine) = self.external_engine.take() else {
+            return;
+        };
+        match self.update_external_engine(&mut engine, ctx) {
+            Ok(()) => self.external_engine = Some(engine),
+            Err(err) => {
+                self.message = Some(err.to_string());
+                self.external_engine_playing = false;
+                self.analysis = false;
+            }
+        }
+    }
+
+    fn update_external_engine(
+        &mut self,
+        engine: &mut ExternalEngine,
+        ctx: &egui::Context,
+    ) -> Result<(), ExternalEngineError> {
+        let best_move = engine.poll()?;
+        ctx.request_repaint_after(Duration::from_millis(100));
+        if let Some(best_move) = best_move
+            && self.external_engine_playing
+        {
+            self.external_engine_playing = false;
+            let played = match best_move {
+                Some(m) => self.state.move_piece(m.start, m.destination, m.promotion),
+                None => self.state.legal_moves().is_empty(),
+            };
+            if !played {
+                // trying again would only get the same answer, so the engine stops playing
+                let m = best_move.map_or("0000".to_string(), |m| m.to_long_algebraic());
+                self.message = Some(format!("engine played illegal move {}", m));
+                self.computer = None;
+            }
+            self.state.selected_square = None;
+            self.pending_promotion = None;
+        }
+        if !engine.is_ready() {
+            return Ok(());
+        }
+
+        if self.replay.is_none()
+            && !self.state.result.is_over()
+            && self.computer == Some(self.state.turn)
+        {
+            if self.external_engine_playing {
+                Ok(())
+            } else {
+                self.external_engine_playing = true;
+                engine.start_search(&self.state, Some(self.computer_thinking_time()))
+            }
+        } else {
+            self.external_engine_playing = false;
+            let displayed_state = self.displayed_state();
+            if !self.analysis || displayed_state.result.is_over() {
+                engine.stop()
+            } else if !engine.is_searching_position(displayed_state) {
+                engine.start_search(displayed_state, None)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn computer_thinking_time(&self) -> Duration {
+        let Some(clock) = &self.state.clock else {
+            return COMPUTER_THINKING_TIME;
+        };
+        let remaining = clock.remaining(self.state.turn, Instant::now());
+        COMPUTER_THINKING_TIME
+            .min(remaining / 30 + clock.time_control.increment / 2)
+            .max(Duration::from_millis(10))
+    }
+
+    /// Against the computer, its reply is taken back together with the own move
+    fn undo(&mut self) {
+        self.stop_computer();
+        self.viewed_position = None;
+        if self.state.undo() {
+            while self.is_computer_turn() && self.state.undo() {}
+            self.state.selected_square = None;
+            self.pending_promotion = None;
+            self.message = None;
+        }
+    }
+
+    fn redo(&mut self) {
+        self.stop_computer();
+        self.viewed_position = None;
+        if self.state.redo() {
+            if self.is_computer_turn() {
+                self.state.redo();
+            }
             self.state.selected_square = None;
-            if piece_moved {
-                self.state.switch_turn();
+            self.pending_promotion = None;
+            self.message = None;
+        }
+    }
+
+    fn play_move_input(&mut self) {
+        let san = self.move_input.trim().to_string();
+        if san.is_empty() || self.is_computer_turn() {
+            return;
+        }
+        match self.state.play_san(&san) {
+            Ok(()) => {
+                self.viewed_position = None;
+                self.state.selected_square = None;
+                self.pending_promotion = None;
+                self.message = None;
             }
+            Err(err) => self.message = Some(err.to_string()),
+        }
+        self.move_input.clear();
+    }
+
+    /// Shows the position after the first `moves` moves of the game, the current
+    /// position returns to the game
+    fn view_position(&mut self, moves: usize) {
+        let played = self.state.move_history.len();
+        if moves >= played {
+            self.viewed_position = None;
+            return;
+        }
+        let mut state = self.state.clone();
+        for _ in moves..played {
+            state.undo();
+        }
+        // the clocks keep showing the time of the game
+        state.clock = self.state.clock;
+        state.selected_square = None;
+        self.viewed_position = Some((moves, state));
+        self.pending_promotion = None;
+        self.dragging = None;
+    }
+
+    fn start_replay(&mut self, pgn: &str) {
+        let replay = parse_pgn(pgn).and_then(|games| {
+            if games.is_empty() {
+                Ok(None)
+            } else {
+                Replay::new(games).map(Some)
+            }
+        });
+        match replay {
+            Ok(Some(replay)) => {
+                self.replay = Some(replay);
+                self.viewed_position = None;
+                self.pending_promotion = None;
+                self.message = None;
+            }
+            Ok(None) => self.message = Some("No games found in PGN".to_string()),
+            Err(err) => self.message = Some(format!("Invalid PGN: {}", err)),
+        }
+    }
+
+    fn show_open_pgn_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.open_pgn_dialog else {
+            return;
+        };
+        match dialog.show(ctx) {
+            DialogResult::Open => {}
+            DialogResult::Confirmed => {
+                match std::fs::read_to_string(&dialog.path) {
+                    Ok(pgn) => self.start_replay(&pgn),
+                    Err(err) => self.message = Some(format!("Could not open PGN: {}", err)),
+                }
+                self.open_pgn_dialog = None;
+            }
+            DialogResult::Cancelled => self.open_pgn_dialog = None,
+        }
+    }
+
+    fn handle_replay_keys(&mut self, ctx: &egui::Context) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        ctx.input(|i| {
+            if i.key_pressed(Key::Home) {
+                replay.first();
+            }
+            if i.key_pressed(Key::ArrowLeft) {
+                replay.previous();
+            }
+            if i.key_pressed(Key::ArrowRight) {
+                replay.next();
+            }
+            if i.key_pressed(Key::End) {
+                replay.last();
+            }
+        });
+    }
+
+    /// The move list beside the board, between the pieces captured by the side at the
+    /// top and the ones captured by the side at the bottom
+    fn show_side_panel(&mut self, ctx: &egui::Context) {
+        let (game, current) = match &self.replay {
+            Some(replay) => (
+                &replay.positions[replay.positions.len() - 1],
+                replay.position_index,
+            ),
+            None => (
+                &self.state,
+                self.viewed_position
+                    .as_ref()
+                    .map_or(self.state.move_history.len(), |(moves, _)| *moves),
+            ),
+        };
+        let scroll = self.move_list_scrolled_to != Some(current);
+        let displayed_state = self.displayed_state();
+        let bottom_side = self.orientation();
+        let mut clicked = None;
+        egui::SidePanel::right("side_panel")
+            .exact_width(MOVE_LIST_WIDTH)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::TopBottomPanel::top("captured_by_top").show_inside(ui, |ui| {
+                    rendering::render_captured_pieces(displayed_state, bottom_side.opposite(), ui);
+                });
+                egui::TopBottomPanel::bottom("captured_by_bottom").show_inside(ui, |ui| {
+                    rendering::render_captured_pieces(displayed_state, bottom_side, ui);
+                });
+                egui::CentralPanel::default().show_inside(ui, |ui| {
+                    clicked = show_move_list(ui, game, current, scroll);
+                });
+            });
+        self.move_list_scrolled_to = Some(current);
+        if let Some(moves) = clicked {
+            match &mut self.replay {
+                Some(replay) => replay.position_index = moves,
+                None => self.view_position(moves),
+            }
+        }
+    }
+
+    /// The buttons below the board, navigation buttons while replaying
+    fn show_controls(&mut self, ui: &mut egui::Ui) {
+        let builder = egui::UiBuilder::new()
+            .max_rect(controls_rect(self.square_size))
+            .layout(egui::Layout::left_to_right(egui::Align::Center));
+        ui.scope_builder(builder, |ui| {
+            if let Some(replay) = &mut self.replay {
+                if ui.button("|<").clicked() {
+                    replay.first();
+                }
+                if ui.button("<").clicked() {
+                    replay.previous();
+                }
+                if ui.button(">").clicked() {
+                    replay.next();
+                }
+                if ui.button(">|").clicked() {
+                    replay.last();
+                }
+                if replay.games.len() > 1 {
+                    let mut load = None;
+                    if ui.button("Prev game").clicked() && replay.game_index > 0 {
+                        load = Some(replay.game_index - 1);
+                    }
+                    if ui.button("Next game").clicked() {
+                        load = Some(replay.game_index + 1);
+                    }
+                    if let Some(Err(err)) = load.map(|index| replay.load_game(index)) {
+                        self.message = Some(format!("Invalid PGN: {}", err));
+                    }
+                }
+                if ui.button("Close").clicked() {
+                    self.replay = None;
+                }
+            } else {
+                ui.menu_button("Game", |ui| self.show_game_menu(ui));
+                if ui
+                    .add_enabled(
+                        !self.state.move_history.is_empty(),
+                        egui::Button::new("Undo"),
+                    )
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(!self.state.redo_stack.is_empty(), egui::Button::new("Redo"))
+                    .clicked()
+                {
+                    self.redo();
+                }
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.move_input)
+                        .hint_text("Move, e.g. Nf3")
+                        .desired_width(90.0),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    self.play_move_input();
+                    response.request_focus();
+                }
+            }
+        });
+    }
+
+    fn show_game_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.button("New game").clicked() {
+            self.start_game(GameState::new_with_default_position());
+            ui.close();
+        }
+        if ui.button("Save PGN").clicked() {
+            self.save_pgn_dialog = Some(SavePgnDialog::new());
+            ui.close();
+        }
+        if ui.button("Open PGN").clicked() {
+            self.open_pgn_dialog = Some(OpenPgnDialog::new());
+            ui.close();
+        }
+        ui.separator();
+        let computer = self.computer;
+        ui.radio_value(&mut self.computer, None, "Two players");
+        ui.radio_value(
+            &mut self.computer,
+            Some(Color::Black),
+            "Play white vs computer",
+        );
+        ui.radio_value(
+            &mut self.computer,
+            Some(Color::White),
+            "Play black vs computer",
+        );
+        if self.computer != computer {
+            // the human side is at the bottom
+            if let Some(computer) = self.computer {
+                self.bottom_side = computer.opposite();
+                self.auto_flip = false;
+            }
+            self.stop_computer();
+            self.state.selected_square = None;
+            self.pending_promotion = None;
+        }
+        if ui.button("Flip board (F)").clicked() {
+            self.flip_board();
+            ui.close();
+        }
+        ui.checkbox(&mut self.auto_flip, "Flip after every move");
+        ui.menu_button("Theme", |ui| self.show_theme_menu(ui));
+        ui.separator();
+        ui.menu_button("Time control", |ui| self.show_time_control_menu(ui));
+        ui.separator();
+        if ui.button("Load UCI engine").clicked() {
+            self.load_engine_dialog = Some(LoadEngineDialog::new());
+            ui.close();
+        }
+        if let Some(engine) = &self.external_engine {
+            ui.label(format!(
+                "Computer: {}",
+                engine.name().unwrap_or("starting engine")
+            ));
+            ui.checkbox(&mut self.analysis, "Analyse");
+            if ui.button("Use built-in engine").clicked() {
+                self.stop_computer();
+                self.external_engine = None;
+                self.analysis = false;
+                ui.close();
+            }
+        }
+    }
+
+    fn show_theme_menu(&mut self, ui: &mut egui::Ui) {
+        for (index, theme) in self.themes.iter().enumerate() {
+            ui.radio_value(&mut self.theme_index, index, &theme.name);
+        }
+        ui.separator();
+        ui.checkbox(&mut self.show_coordinates, "Coordinates");
+        if ui.button(format!("Reload {}", THEMES_FILE)).clicked() {
+            let name = self.theme().name.clone();
+            let (themes, error) = load_themes();
+            self.themes = themes;
+            self.theme_index = self
+                .themes
+                .iter()
+                .position(|theme| theme.name == name)
+                .unwrap_or(0);
+            self.message = error;
+            ui.close();
+        }
+    }
+
+    /// Settings for the clocks of the next new game
+    fn show_time_control_menu(&mut self, ui: &mut egui::Ui) {
+        let mut enabled = self.time_control.is_some();
+        ui.checkbox(&mut enabled, "Play with clocks");
+        if !enabled {
+            self.time_control = None;
+            return;
+        }
+        let time_control = self.time_control.get_or_insert(TimeControl::new(
+            Duration::from_secs(5 * 60),
+            Duration::from_secs(3),
+            TimeControlMode::Fischer,
+        ));
+        let mut minutes = time_control.base.as_secs() / 60;
+        let mut seconds = time_control.increment.as_secs();
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut minutes).range(1..=180));
+            ui.label("minutes");
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut seconds).range(0..=60));
+            ui.label("seconds per move");
+        });
+        time_control.base = Duration::from_secs(minutes * 60);
+        time_control.increment = Duration::from_secs(seconds);
+        ui.radio_value(
+            &mut time_control.mode,
+            TimeControlMode::Fischer,
+            "Fischer increment",
+        );
+        ui.radio_value(
+            &mut time_control.mode,
+            TimeControlMode::Bronstein,
+            "Bronstein delay",
+        );
+        ui.radio_value(
+            &mut time_control.mode,
+            TimeControlMode::SimpleDelay,
+            "Simple delay",
+        );
+        ui.label("Applies to the next new game");
+    }
+
+    fn show_load_engine_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.load_engine_dialog else {
+            return;
+        };
+        match dialog.show(ctx) {
+            DialogResult::Open => {}
+            DialogResult::Confirmed => {
+                let path = dialog.path.trim().to_string();
+                self.load_engine_dialog = None;
+                self.stop_computer();
+                match ExternalEngine::spawn(&path) {
+                    Ok(engine) => self.external_engine = Some(engine),
+                    Err(err) => self.message = Some(err.to_string()),
+                }
+            }
+            DialogResult::Cancelled => self.load_engine_dialog = None,
+        }
+    }
+
+    fn show_save_pgn_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.save_pgn_dialog else {
+            return;
+        };
+        match dialog.show(ctx) {
+            DialogResult::Open => {}
+            DialogResult::Confirmed => {
+                let pgn = export_pgn(&self.state, &dialog.tags);
+                self.message = Some(match std::fs::write(&dialog.path, pgn) {
+                    Ok(()) => format!("Saved PGN to {}", dialog.path),
+                    Err(err) => format!("Could not save PGN: {}", err),
+                });
+                self.save_pgn_dialog = None;
+            }
+            DialogResult::Cancelled => self.save_pgn_dialog = None,
         }
     }
 }
 
 impl eframe::App for ChessApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::D)) {
+            self.state.claim_draw();
+        }
+        if !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(egui::Key::F)) {
+            self.flip_board();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&SAVE_PGN_SHORTCUT)) {
+            self.save_pgn_dialog = Some(SavePgnDialog::new());
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&OPEN_PGN_SHORTCUT)) {
+            self.open_pgn_dialog = Some(OpenPgnDialog::new());
+        }
+        if self.replay.is_none() && !ctx.wants_keyboard_input() {
+            if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+                self.undo();
+            }
+            if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+                self.redo();
+            }
+        }
+        self.handle_clipboard(ctx);
+        self.handle_replay_keys(ctx);
+        self.show_save_pgn_dialog(ctx);
+        self.show_open_pgn_dialog(ctx);
+        self.show_load_engine_dialog(ctx);
+        self.state.check_flag(Instant::now());
+        if self
+            .state
+            .clock
+            .is_some_and(|clock| clock.running().is_some())
+        {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        self.play_computer_move(ctx);
+        self.show_side_panel(ctx);
+        // the space left of the move list
+        let board_area = ctx.available_rect().size();
         egui::CentralPanel::default().show(ctx, |ui| {
-            rendering::resize(ctx);
-            let board_rect = Rect::from_min_size(Pos2::ZERO, board_size_vec2());
-            let viewport_rect = Rect::from_min_size(Pos2::ZERO, viewport_size_vec2());
-            let response = ui.allocate_rect(board_rect, Sense::click());
+            self.square_size = square_size_for(board_area);
+            let board_rect = Rect::from_min_size(Pos2::ZERO, board_size_vec2(self.square_size));
+            let viewport_rect = ctx.screen_rect();
+            let response = ui.allocate_rect(board_rect, Sense::click_and_drag());
+            let pointer = response
+                .interact_pointer_pos()
+                .map(|pos| (pos - response.rect.min).to_pos2());
+            if let (Some((start, _)), Some(pos)) = (self.dragging, pointer) {
+                self.dragging = Some((start, pos));
+            }
             let mut painter = ui.painter_at(viewport_rect);
             rendering::render(self, ui, &mut painter);
-            if response.clicked() {
-                self.on_click(
-                    (response.interact_pointer_pos().unwrap() - response.rect.min).to_pos2(),
-                );
+            self.show_controls(ui);
+            if let Some(pos) = pointer {
+                // the square under the button press, the cursor may have moved on already
+                let origin = ctx
+                    .input(|i| i.pointer.press_origin())
+                    .map_or(pos, |origin| (origin - response.rect.min).to_pos2());
+                if response.clicked() {
+                    self.on_click(pos);
+                } else if response.secondary_clicked() {
+                    self.on_annotation_start(pos);
+                    self.on_annotation_end(pos);
+                } else if response.drag_started_by(PointerButton::Primary) {
+                    self.on_drag_start(origin);
+                } else if response.drag_started_by(PointerButton::Secondary) {
+                    self.on_annotation_start(origin);
+                } else if response.drag_stopped_by(PointerButton::Primary) {
+                    self.on_drag_end(pos);
+                } else if response.drag_stopped_by(PointerButton::Secondary) {
+                    self.on_annotation_end(pos);
+                }
             }
         });
     }
 }
+
+/// The moves of the game in numbered pairs. The move that led to the displayed position,
+/// `current` moves into the game, is highlighted and scrolled to if `scroll` is set.
+/// Returns the number of moves up to the one that was clicked.
+fn show_move_list(
+    ui: &mut egui::Ui,
+    game: &GameState,
+    current: usize,
+    scroll: bool,
+) -> Option<usize> {
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .auto_shrink(false)
+        .show(ui, |ui| {
+            egui::Grid::new("moves")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, record) in game.move_history.iter().enumerate() {
+                        let moves = index + 1;
+                        let number = record.previous_board_data.fullmove_number;
+                        if record.piece.color == Color::White {
+                            ui.label(format!("{}.", number));
+                        } else if index == 0 {
+                            // the game started with black to move
+                            ui.label(format!("{}...", number));
+                            ui.label("");
+                        }
+                        let response = ui.selectable_label(moves == current, &record.san);
+                        if response.clicked() {
+                            clicked = Some(moves);
+                        }
+                        if moves == current && scroll {
+                            response.scroll_to_me(None);
+                        }
+                        if record.piece.color == Color::Black {
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+    clicked
+}
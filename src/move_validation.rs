@@ -1,2 +1,3 @@
 pub mod movement;
+pub mod perft;
 pub mod validator;
@@ -1,41 +1,93 @@
 use crate::{
-    board::{Board, BoardExt, BoardIndex, Color, PieceType},
+    board::{Board, BoardExt, Color, PieceType},
+    square::Square,
     state::{AdditionalBoardData, GameState},
 };
 
 #[derive(Clone)]
 pub struct Movement {
-    pub start: BoardIndex,
-    pub destination: BoardIndex,
+    pub start: Square,
+    pub destination: Square,
+    /// The piece a pawn is promoted to when reaching the last row
+    pub promotion: Option<PieceType>,
     pub movement_info: MovementInformation,
 }
 
 impl Movement {
     pub fn from_with_state(
-        start: BoardIndex,
-        destination: BoardIndex,
+        start: Square,
+        destination: Square,
+        promotion: Option<PieceType>,
         state: &GameState,
         board_data: AdditionalBoardData,
     ) -> Self {
+        let piece_type = state.board.piece_at(start).unwrap().piece_type;
         Self {
-            start: start,
-            destination: destination,
+            start,
+            destination,
+            promotion,
             movement_info: MovementInformation {
                 capturing: state.board.piece_at(destination).is_some()
-                    || state
-                        .additional_board_data
-                        .en_passant_square
-                        .is_some_and(|sq| sq == destination),
-                board: state.board.clone(),
-                piece_type: state.board.piece_at(start).unwrap().piece_type,
+                    || (piece_type == PieceType::Pawn
+                        && state
+                            .additional_board_data
+                            .en_passant_square
+                            .is_some_and(|sq| sq == destination)),
+                board: state.board,
+                piece_type,
                 piece_color: state.board.piece_at(start).unwrap().color,
                 turn: state.turn,
-                board_data: board_data,
+                board_data,
             },
         }
     }
 }
 
+/// A move without the position it is played in, small enough to be copied around
+/// during move generation and search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlainMove {
+    pub start: Square,
+    pub destination: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl PlainMove {
+    /// The move in the long algebraic notation used by UCI, e.g. `e2e4` or `e7e8q`
+    pub fn to_long_algebraic(self) -> String {
+        let mut text = format!("{}{}", self.start, self.destination);
+        if let Some(promotion) = self.promotion {
+            text.push(match promotion {
+                PieceType::Knight => 'n',
+                PieceType::Bishop => 'b',
+                PieceType::Rook => 'r',
+                _ => 'q',
+            });
+        }
+        text
+    }
+
+    /// Parses a move in long algebraic notation like `e2e4` or `e7e8q`.
+    /// Does not check whether the move is legal.
+    pub fn from_long_algebraic(text: &str) -> Option<Self> {
+        let start = Square::from_algebraic(text.get(0..2)?)?;
+        let destination = Square::from_algebraic(text.get(2..4)?)?;
+        let promotion = match text.get(4..)? {
+            "" => None,
+            "q" => Some(PieceType::Queen),
+            "n" => Some(PieceType::Knight),
+            "b" => Some(PieceType::Bishop),
+            "r" => Some(PieceType::Rook),
+            _ => return None,
+        };
+        Some(Self {
+            start,
+            destination,
+            promotion,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct MovementInformation {
     pub capturing: bool,
@@ -0,0 +1,155 @@
+use crate::{
+    board::{Board, Color},
+    move_validation::{
+        movement::PlainMove,
+        validator::{generate_legal_moves, play_move},
+    },
+    state::AdditionalBoardData,
+};
+
+/// Counts the leaf nodes of the legal move tree down to `depth`
+pub fn perft(board: &Board, board_data: &AdditionalBoardData, turn: Color, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = generate_legal_moves(board, board_data, turn);
+    // the leaves below the last level are the legal moves themselves
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|m| {
+            let (board, board_data) = position_after(board, board_data, m);
+            perft(&board, &board_data, turn.opposite(), depth - 1)
+        })
+        .sum()
+}
+
+/// Like `perft`, but returns the count below every root move separately, which
+/// makes it possible to find the move where generation differs from a reference
+pub fn divide(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    turn: Color,
+    depth: u32,
+) -> Vec<(PlainMove, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    generate_legal_moves(board, board_data, turn)
+        .into_iter()
+        .map(|m| {
+            let (board, board_data) = position_after(board, board_data, m);
+            (m, perft(&board, &board_data, turn.opposite(), depth - 1))
+        })
+        .collect()
+}
+
+fn position_after(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    m: PlainMove,
+) -> (Board, AdditionalBoardData) {
+    let mut board = *board;
+    let mut board_data = *board_data;
+    play_move(
+        &mut board,
+        &mut board_data,
+        m.start,
+        m.destination,
+        m.promotion,
+    );
+    (board, board_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{DEFAULT_POSITION_FEN, parse_fen};
+
+    // https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_4_MIRRORED: &str =
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn perft_fen(fen: &str, depth: u32) -> u64 {
+        let (board, turn, board_data) = parse_fen(fen).unwrap();
+        perft(&board, &board_data, turn, depth)
+    }
+
+    #[test]
+    fn start_position() {
+        assert_eq!(perft_fen(DEFAULT_POSITION_FEN, 1), 20);
+        assert_eq!(perft_fen(DEFAULT_POSITION_FEN, 2), 400);
+        assert_eq!(perft_fen(DEFAULT_POSITION_FEN, 3), 8902);
+        assert_eq!(perft_fen(DEFAULT_POSITION_FEN, 4), 197281);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_eq!(perft_fen(KIWIPETE, 1), 48);
+        assert_eq!(perft_fen(KIWIPETE, 2), 2039);
+        assert_eq!(perft_fen(KIWIPETE, 3), 97862);
+    }
+
+    #[test]
+    fn position_3() {
+        assert_eq!(perft_fen(POSITION_3, 1), 14);
+        assert_eq!(perft_fen(POSITION_3, 2), 191);
+        assert_eq!(perft_fen(POSITION_3, 3), 2812);
+        assert_eq!(perft_fen(POSITION_3, 4), 43238);
+        assert_eq!(perft_fen(POSITION_3, 5), 674624);
+    }
+
+    #[test]
+    fn position_4() {
+        assert_eq!(perft_fen(POSITION_4, 1), 6);
+        assert_eq!(perft_fen(POSITION_4, 2), 264);
+        assert_eq!(perft_fen(POSITION_4, 3), 9467);
+        assert_eq!(perft_fen(POSITION_4_MIRRORED, 3), 9467);
+    }
+
+    #[test]
+    fn position_5() {
+        assert_eq!(perft_fen(POSITION_5, 1), 44);
+        assert_eq!(perft_fen(POSITION_5, 2), 1486);
+        assert_eq!(perft_fen(POSITION_5, 3), 62379);
+    }
+
+    #[test]
+    fn position_6() {
+        assert_eq!(perft_fen(POSITION_6, 1), 46);
+        assert_eq!(perft_fen(POSITION_6, 2), 2079);
+        assert_eq!(perft_fen(POSITION_6, 3), 89890);
+    }
+
+    #[test]
+    fn divide_sums_up_to_perft() {
+        let (board, turn, board_data) = parse_fen(KIWIPETE).unwrap();
+        let divided = divide(&board, &board_data, turn, 2);
+        assert_eq!(divided.len(), 48);
+        assert_eq!(divided.iter().map(|(_, count)| count).sum::<u64>(), 2039);
+        let castling = divided
+            .iter()
+            .find(|(m, _)| m.to_long_algebraic() == "e1g1")
+            .unwrap();
+        assert_eq!(castling.1, 43);
+    }
+
+    /// Too slow for unoptimized builds, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn deep() {
+        assert_eq!(perft_fen(DEFAULT_POSITION_FEN, 5), 4865609);
+        assert_eq!(perft_fen(KIWIPETE, 4), 4085603);
+        assert_eq!(perft_fen(POSITION_4, 4), 422333);
+        assert_eq!(perft_fen(POSITION_5, 4), 2103487);
+        assert_eq!(perft_fen(POSITION_6, 4), 3894594);
+    }
+}
@@ -1,12 +1,22 @@
 use crate::{
-    BOARD_SQUARES,
-    board::{Board, BoardExt, BoardIndex, BoardIndexExt, BoardIndexXYExt, Color, Piece, PieceType},
-    move_validation::movement::Movement,
+    bitboard::{
+        Bitboard, bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks,
+        rook_attacks, square_bit, squares,
+    },
+    board::{Board, BoardExt, Color, Piece, PieceType},
+    move_validation::movement::{Movement, PlainMove},
+    square::{File, Rank, Square},
     state::AdditionalBoardData,
 };
 
 pub fn validate_move(m: Movement) -> bool {
-    if m.movement_info.piece_color != m.movement_info.turn {
+    let promotion_required = m.movement_info.piece_type == PieceType::Pawn
+        && is_last_row(m.destination, m.movement_info.piece_color);
+    let promotion_valid = match m.promotion {
+        None => !promotion_required,
+        Some(piece_type) => promotion_required && PROMOTION_PIECES.contains(&piece_type),
+    };
+    if m.movement_info.piece_color != m.movement_info.turn || !promotion_valid {
         false
     } else {
         generate_piece_map(
@@ -19,214 +29,285 @@ pub fn validate_move(m: Movement) -> bool {
     }
 }
 
-/// Creates a list of all possible destinations
+/// The piece types a pawn may be promoted to
+pub const PROMOTION_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Bishop,
+];
+
+/// Returns `true` if `square` is on the rank a pawn of `color` promotes on
+pub fn is_last_row(square: Square, color: Color) -> bool {
+    square.rank() == Rank::EIGHTH.relative_to(color)
+}
+
+/// Creates a list of all legal destinations, i.e. the pseudo-legal destinations
+/// that do not leave the moving side's king in check
 pub fn generate_piece_map(
     board: &Board,
     board_data: &AdditionalBoardData,
     turn: Color,
-    piece_index: BoardIndex,
-) -> Vec<BoardIndex> {
-    let mut piece_map: Vec<BoardIndex> = Vec::new();
+    piece_square: Square,
+) -> Vec<Square> {
+    squares(legal_destinations(board, board_data, turn, piece_square)).collect()
+}
 
-    let piece = board.piece_at(piece_index);
-    if piece.is_none() {
-        return piece_map;
+/// The legal destinations of the piece as a bitboard, see `generate_piece_map`
+pub fn legal_destinations(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    turn: Color,
+    piece_square: Square,
+) -> Bitboard {
+    let mut destinations = pseudo_legal_destinations(board, board_data, turn, piece_square);
+    for destination in squares(destinations) {
+        if is_in_check(&board_after_move(board, piece_square, destination), turn) {
+            destinations &= !square_bit(destination);
+        }
     }
+    destinations
+}
 
-    let piece = piece.unwrap();
+/// All possible destinations of the piece as a bitboard, without checking if the move
+/// would leave the own king in check
+pub fn pseudo_legal_destinations(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    turn: Color,
+    piece_square: Square,
+) -> Bitboard {
+    let Some(piece) = board.piece_at(piece_square) else {
+        return 0;
+    };
     if piece.color != turn {
-        return piece_map;
+        return 0;
     }
-    let xy_index = piece_index.to_xy();
+    let own = board.color(piece.color);
+    let enemy = board.color(piece.color.opposite());
+    let occupied = own | enemy;
     match piece.piece_type {
         PieceType::Pawn => {
-            let mut may_push_1_square = false;
-            let color_modifier = if piece.color == Color::White { -1 } else { 1 };
-            if (xy_index.1 as i32 + color_modifier) >= 0 {
-                let one_ahead_index =
-                    (xy_index.0, (xy_index.1 as i32 + color_modifier) as u16).to_index();
-                let one_ahead = board.piece_at(one_ahead_index);
-                if one_ahead.is_none() {
-                    may_push_1_square = true;
-                    piece_map.push(one_ahead_index);
-                }
-
-                let one_ahead_xy = one_ahead_index.to_xy();
-                if one_ahead_xy.0 > 0 {
-                    let take_xy = (one_ahead_xy.0 - 1, one_ahead_xy.1);
-                    if board
-                        .piece_at(take_xy.to_index())
-                        .is_some_and(|p| p.color != piece.color)
-                    {
-                        piece_map.push(take_xy.to_index());
-                    } else if board_data
-                        .en_passant_square
-                        .is_some_and(|en_passant_square| en_passant_square == take_xy.to_index())
-                    {
-                        piece_map.push(take_xy.to_index());
-                    }
-                }
-                if one_ahead_xy.0 < BOARD_SQUARES - 1 {
-                    let take_xy = (one_ahead_xy.0 + 1, one_ahead_xy.1);
-                    if board
-                        .piece_at(take_xy.to_index())
-                        .is_some_and(|p| p.color != piece.color)
-                    {
-                        piece_map.push(take_xy.to_index());
-                    } else if board_data
-                        .en_passant_square
-                        .is_some_and(|en_passant_square| en_passant_square == take_xy.to_index())
-                    {
-                        piece_map.push(take_xy.to_index());
-                    }
-                }
-            }
-
-            let may_push_2_squares = if piece.color == Color::White {
-                xy_index.1 == 6
-            } else {
-                xy_index.1 == 1
-            };
-            if (xy_index.1 as i32 + color_modifier * 2) >= 0
-                && may_push_2_squares
-                && may_push_1_square
+            let en_passant = board_data.en_passant_square.map_or(0, square_bit);
+            let mut destinations = pawn_attacks(piece.color, piece_square) & (enemy | en_passant);
+            let forward = piece.color.pawn_direction();
+            if let Some(one_ahead) = piece_square.offset(0, forward)
+                && square_bit(one_ahead) & occupied == 0
             {
-                let two_ahead_index =
-                    (xy_index.0, (xy_index.1 as i32 + color_modifier * 2) as u16).to_index();
-                let two_ahead = board.piece_at(two_ahead_index);
-                if two_ahead.is_none() {
-                    piece_map.push(two_ahead_index);
+                destinations |= square_bit(one_ahead);
+                if piece_square.rank() == Rank::SECOND.relative_to(piece.color)
+                    && let Some(two_ahead) = one_ahead.offset(0, forward)
+                    && square_bit(two_ahead) & occupied == 0
+                {
+                    destinations |= square_bit(two_ahead);
                 }
             }
+            destinations
         }
         PieceType::King => {
-            let adjacent_squares = adjacent_king_squares(piece_index);
-            for ele in adjacent_squares {
-                if board.piece_at(ele).is_none_or(|p| p.color != piece.color) {
-                    piece_map.push(ele);
-                }
+            let mut destinations = king_attacks(piece_square) & !own;
+            for destination in castling_destinations(board, board_data, piece.color, piece_square) {
+                destinations |= square_bit(destination);
             }
+            destinations
         }
-        PieceType::Knight => {
-            let adjacent_squares = adjacent_knight_squares(piece_index);
-            for ele in adjacent_squares {
-                if board.piece_at(ele).is_none_or(|p| p.color != piece.color) {
-                    piece_map.push(ele);
+        PieceType::Knight => knight_attacks(piece_square) & !own,
+        PieceType::Rook => rook_attacks(piece_square, occupied) & !own,
+        PieceType::Bishop => bishop_attacks(piece_square, occupied) & !own,
+        PieceType::Queen => queen_attacks(piece_square, occupied) & !own,
+    }
+}
+
+/// Creates a list of all legal moves of the side to move, with one move per promotion piece
+pub fn generate_legal_moves(
+    board: &Board,
+    board_data: &AdditionalBoardData,
+    turn: Color,
+) -> Vec<PlainMove> {
+    let mut moves = Vec::new();
+    for start in squares(board.color(turn)) {
+        let is_pawn = board.pieces[PieceType::Pawn as usize] & square_bit(start) != 0;
+        for destination in squares(legal_destinations(board, board_data, turn, start)) {
+            if is_pawn && is_last_row(destination, turn) {
+                for promotion in PROMOTION_PIECES {
+                    moves.push(PlainMove {
+                        start,
+                        destination,
+                        promotion: Some(promotion),
+                    });
                 }
-            }
-        }
-        PieceType::Rook => {
-            let modifiers = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-            for ele in get_piece_map_from_modifiers(board, piece_index, piece, &modifiers) {
-                piece_map.push(ele);
-            }
-        }
-        PieceType::Bishop => {
-            let modifiers = [(1, 1), (-1, -1), (-1, 1), (1, -1)];
-            for ele in get_piece_map_from_modifiers(board, piece_index, piece, &modifiers) {
-                piece_map.push(ele);
-            }
-        }
-        PieceType::Queen => {
-            let modifiers = [
-                (1, 0),
-                (-1, 0),
-                (0, 1),
-                (0, -1),
-                (1, 1),
-                (-1, -1),
-                (-1, 1),
-                (1, -1),
-            ];
-            for ele in get_piece_map_from_modifiers(board, piece_index, piece, &modifiers) {
-                piece_map.push(ele);
+            } else {
+                moves.push(PlainMove {
+                    start,
+                    destination,
+                    promotion: None,
+                });
             }
         }
     }
-    piece_map
-}
-
-fn adjacent_king_squares(index: BoardIndex) -> Vec<BoardIndex> {
-    let modifiers = [
-        (-1, 0),
-        (1, 0),
-        (0, -1),
-        (0, 1),
-        (1, 1),
-        (-1, -1),
-        (-1, 1),
-        (1, -1),
-    ];
-    adjacent_squares_from_modifiers(index, &modifiers)
-}
-
-fn adjacent_knight_squares(index: BoardIndex) -> Vec<BoardIndex> {
-    let modifiers = [
-        (2, 1),
-        (1, 2),
-        (-1, -2),
-        (-2, -1),
-        (-2, 1),
-        (2, -1),
-        (1, -2),
-        (-1, 2),
-    ];
-    adjacent_squares_from_modifiers(index, &modifiers)
-}
-
-fn adjacent_squares_from_modifiers(index: BoardIndex, modifiers: &[(i32, i32)]) -> Vec<BoardIndex> {
-    let xy_index = index.to_xy();
-    let mut adjacent = Vec::new();
-    for ele in modifiers {
-        let new_index = (xy_index.0 as i32 + ele.0, xy_index.1 as i32 + ele.1);
-        if new_index.0 < 0
-            || new_index.0 >= BOARD_SQUARES as i32
-            || new_index.1 < 0
-            || new_index.1 >= BOARD_SQUARES as i32
+    moves
+}
+
+/// Returns `true` if any piece of color `turn` has at least one legal move
+pub fn has_legal_moves(board: &Board, board_data: &AdditionalBoardData, turn: Color) -> bool {
+    squares(board.color(turn))
+        .any(|square| legal_destinations(board, board_data, turn, square) != 0)
+}
+
+/// Returns `true` if the king of `color` is attacked
+pub fn is_in_check(board: &Board, color: Color) -> bool {
+    find_king(board, color).is_some_and(|king| is_square_attacked(board, king, color.opposite()))
+}
+
+pub fn find_king(board: &Board, color: Color) -> Option<Square> {
+    squares(board.pieces_of(PieceType::King, color)).next()
+}
+
+/// Returns `true` if any piece of color `by` attacks the square.
+/// The square itself may be empty or occupied by a piece of either color.
+pub fn is_square_attacked(board: &Board, square: Square, by: Color) -> bool {
+    let occupied = board.occupied();
+    let queens = board.pieces_of(PieceType::Queen, by);
+    // a pawn of `by` attacks the square if a pawn of the other color on the square would attack it
+    knight_attacks(square) & board.pieces_of(PieceType::Knight, by) != 0
+        || king_attacks(square) & board.pieces_of(PieceType::King, by) != 0
+        || pawn_attacks(by.opposite(), square) & board.pieces_of(PieceType::Pawn, by) != 0
+        || rook_attacks(square, occupied) & (board.pieces_of(PieceType::Rook, by) | queens) != 0
+        || bishop_attacks(square, occupied) & (board.pieces_of(PieceType::Bishop, by) | queens) != 0
+}
+
+/// Plays the move on the board and updates the additional board data: the en passant square,
+/// the castling rights and the clocks. Does not validate the move.
+pub fn play_move(
+    board: &mut Board,
+    board_data: &mut AdditionalBoardData,
+    start: Square,
+    destination: Square,
+    promotion: Option<PieceType>,
+) {
+    let Some(piece) = board.piece_at(start) else {
+        return;
+    };
+    let is_pawn = piece.piece_type == PieceType::Pawn;
+    // a pawn moving diagonally always captures, either normally or en passant
+    let capturing =
+        board.piece_at(destination).is_some() || (is_pawn && start.file() != destination.file());
+
+    // only set if the pawn moved 2 squares, the en passant square is the one it skipped
+    board_data.en_passant_square = if is_pawn && start.rank().distance(destination.rank()) == 2 {
+        start.offset(0, piece.color.pawn_direction())
+    } else {
+        None
+    };
+    board_data.update_castling_status(start, destination);
+    if is_pawn || capturing {
+        board_data.halfmove_clock = 0;
+    } else {
+        board_data.halfmove_clock += 1;
+    }
+    if piece.color == Color::Black {
+        board_data.fullmove_number += 1;
+    }
+
+    apply_move(board, start, destination, promotion);
+}
+
+/// Returns a copy of the board with the move applied. Does not validate the move.
+fn board_after_move(board: &Board, start: Square, destination: Square) -> Board {
+    let mut new_board = *board;
+    apply_move(&mut new_board, start, destination, None);
+    new_board
+}
+
+/// Moves the piece from `start` to `destination`, including the removal of a pawn
+/// taken en passant, the rook move of a castling king and the replacement of a
+/// promoted pawn. Does not validate the move.
+pub fn apply_move(
+    board: &mut Board,
+    start: Square,
+    destination: Square,
+    promotion: Option<PieceType>,
+) {
+    let piece = board.piece_at(start);
+    match piece.map(|p| p.piece_type) {
+        // a pawn moving diagonally onto an empty square is taking en passant
+        Some(PieceType::Pawn)
+            if start.file() != destination.file() && board.piece_at(destination).is_none() =>
         {
-            continue;
+            board.set_piece(en_passant_capture_square(start, destination), None);
         }
-        adjacent.push((new_index.0 as u16, new_index.1 as u16).to_index());
+        // a king moving two squares is castling
+        Some(PieceType::King) if start.file().distance(destination.file()) == 2 => {
+            let (rook_start, rook_destination) = castling_rook_squares(start, destination);
+            board.set_piece(rook_destination, board.piece_at(rook_start));
+            board.set_piece(rook_start, None);
+        }
+        _ => {}
     }
-    adjacent
+    board.set_piece(
+        destination,
+        match promotion {
+            Some(piece_type) => piece.map(|p| Piece::new(piece_type, p.color)),
+            None => piece,
+        },
+    );
+    board.set_piece(start, None);
 }
 
-fn validate_i32_pos(pos: (i32, i32)) -> bool {
-    pos.0 >= 0 && pos.1 >= 0 && pos.0 < BOARD_SQUARES as i32 && pos.1 < BOARD_SQUARES as i32
+/// The square of the pawn taken en passant by the pawn moving from `start` to `destination`
+pub fn en_passant_capture_square(start: Square, destination: Square) -> Square {
+    Square::new(destination.file(), start.rank())
 }
 
-fn get_piece_map_from_modifiers(
+/// The start and destination of the rook when the king castles from `start` to `destination`
+pub fn castling_rook_squares(start: Square, destination: Square) -> (Square, Square) {
+    let rank = start.rank();
+    if destination.file() > start.file() {
+        (Square::new(File::H, rank), Square::new(File::F, rank))
+    } else {
+        (Square::new(File::A, rank), Square::new(File::D, rank))
+    }
+}
+
+/// Returns the destinations of the king on `king_square` that are castling moves.
+/// The king may not be in check and may not pass through an attacked square;
+/// whether the destination itself is attacked is left to the legality filter.
+fn castling_destinations(
     board: &Board,
-    index: BoardIndex,
-    piece: Piece,
-    modifiers: &[(i32, i32)],
-) -> Vec<BoardIndex> {
-    let mut piece_map = Vec::new();
-    let xy_index = index.to_xy();
-    for modifier in modifiers {
-        let mut current_pos = (
-            xy_index.0 as i32 + modifier.0,
-            xy_index.1 as i32 + modifier.1,
-        );
-        let mut limit = BOARD_SQUARES as i32;
-        while limit >= 0 {
-            if !validate_i32_pos(current_pos) {
-                break;
-            }
-            let current_pos_index = (current_pos.0 as u16, current_pos.1 as u16).to_index();
-            let current_piece = board.piece_at(current_pos_index);
-            if current_piece.is_none() {
-                piece_map.push(current_pos_index);
-            } else if current_piece.is_some_and(|p| p.color != piece.color) {
-                piece_map.push(current_pos_index);
-                break;
+    board_data: &AdditionalBoardData,
+    color: Color,
+    king_square: Square,
+) -> Vec<Square> {
+    let mut destinations = Vec::new();
+    let home_rank = Rank::FIRST.relative_to(color);
+    if king_square != Square::new(File::E, home_rank)
+        || is_square_attacked(board, king_square, color.opposite())
+    {
+        return destinations;
+    }
+
+    for kingside in [true, false] {
+        if !board_data.can_castle(color, kingside) {
+            continue;
+        }
+        let (rook_file, empty_files, passed_file, destination_file): (File, &[File], File, File) =
+            if kingside {
+                (File::H, &[File::F, File::G], File::F, File::G)
             } else {
-                break;
-            }
-            current_pos.0 += modifier.0;
-            current_pos.1 += modifier.1;
-            limit -= 1;
+                (File::A, &[File::B, File::C, File::D], File::D, File::C)
+            };
+        let rook_present = board
+            .piece_at(Square::new(rook_file, home_rank))
+            .is_some_and(|p| p.piece_type == PieceType::Rook && p.color == color);
+        let path_empty = empty_files
+            .iter()
+            .all(|file| board.piece_at(Square::new(*file, home_rank)).is_none());
+        if rook_present
+            && path_empty
+            && !is_square_attacked(board, Square::new(passed_file, home_rank), color.opposite())
+        {
+            destinations.push(Square::new(destination_file, home_rank));
         }
     }
-    piece_map
+    destinations
 }
@@ -0,0 +1,606 @@
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    board::Color,
+    fen::{DEFAULT_POSITION_FEN, FenError, parse_fen},
+    game_result::GameResult,
+    san::SanError,
+    state::GameState,
+};
+
+/// PGN export lines should not be longer than this
+const MAX_LINE_LENGTH: usize = 79;
+
+/// The Seven Tag Roster except for the result, which is taken from the game
+#[derive(Clone)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnTags {
+    fn default() -> Self {
+        Self {
+            event: "Casual game".to_string(),
+            site: "guh-chess".to_string(),
+            date: today_pgn_date(),
+            round: "-".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// The PGN result token of the game: `1-0`, `0-1`, `1/2-1/2` or `*` if it is still going on
+pub fn result_token(result: &GameResult) -> &'static str {
+    match result {
+        GameResult::Ongoing => "*",
+        GameResult::WhiteWins(_) => "1-0",
+        GameResult::BlackWins(_) => "0-1",
+        GameResult::Draw(_) => "1/2-1/2",
+    }
+}
+
+/// Exports the game with its tags, the moves in SAN including comments and the result
+pub fn export_pgn(state: &GameState, tags: &PgnTags) -> String {
+    let result = result_token(&state.result);
+    let mut pgn = String::new();
+    for (name, value) in [
+        ("Event", &tags.event),
+        ("Site", &tags.site),
+        ("Date", &tags.date),
+        ("Round", &tags.round),
+        ("White", &tags.white),
+        ("Black", &tags.black),
+    ] {
+        pgn.push_str(&tag_line(name, value));
+    }
+    pgn.push_str(&tag_line("Result", result));
+    let starts_from_setup =
+        !state.initial_fen.is_empty() && state.initial_fen != DEFAULT_POSITION_FEN;
+    if starts_from_setup {
+        pgn.push_str(&tag_line("SetUp", "1"));
+        pgn.push_str(&tag_line("FEN", &state.initial_fen));
+    }
+    pgn.push('\n');
+
+    let (mut turn, mut move_number) = match parse_fen(&state.initial_fen) {
+        Ok((_, turn, board_data)) => (turn, board_data.fullmove_number),
+        Err(_) => (Color::White, 1),
+    };
+    let mut tokens = Vec::new();
+    // a move number is needed for black after a comment or at the very start
+    let mut needs_move_number = true;
+    for record in &state.move_history {
+        if turn == Color::White {
+            tokens.push(format!("{}.", move_number));
+        } else if needs_move_number {
+            tokens.push(format!("{}...", move_number));
+        }
+        tokens.push(record.san.clone());
+        needs_move_number = false;
+        if let Some(comment) = &record.comment {
+            tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            needs_move_number = true;
+        }
+        if turn == Color::Black {
+            move_number += 1;
+        }
+        turn = turn.opposite();
+    }
+    if state.result.is_over() {
+        tokens.push(format!("{{{}}}", state.result));
+    }
+    tokens.push(result.to_string());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+fn tag_line(name: &str, value: &str) -> String {
+    format!(
+        "[{} \"{}\"]\n",
+        name,
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Today's date in the PGN format `YYYY.MM.DD`
+pub fn today_pgn_date() -> String {
+    let Ok(since_epoch) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // convert days since 1970-01-01 to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (since_epoch.as_secs() / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+#[derive(Clone, Default)]
+pub struct PgnGame {
+    /// The tag pairs in the order they appear
+    pub tags: Vec<(String, String)>,
+    /// Comments before the first move
+    pub comments: Vec<String>,
+    /// The main line
+    pub moves: Vec<PgnMove>,
+    /// The result token, `*` if none was given
+    pub result: String,
+}
+
+#[derive(Clone, Default)]
+pub struct PgnMove {
+    pub san: String,
+    /// Numeric Annotation Glyphs, with `!`, `?` and similar suffixes converted to their NAGs
+    pub nags: Vec<u8>,
+    /// Comments after the move
+    pub comments: Vec<String>,
+    /// Alternatives to this move, each a line starting at the position before this move
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    InvalidTag(String),
+    UnterminatedComment,
+    UnbalancedVariation,
+    InvalidNag(String),
+    InvalidFen(FenError),
+    /// The SAN move at the given ply of the game, counted from 1, can not be played.
+    /// The move may be in the main line or in a variation.
+    InvalidMove {
+        ply: usize,
+        error: SanError,
+    },
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag pair '{}'", tag),
+            PgnError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+            PgnError::UnbalancedVariation => write!(f, "unbalanced parentheses in variations"),
+            PgnError::InvalidNag(nag) => write!(f, "invalid annotation glyph '{}'", nag),
+            PgnError::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            PgnError::InvalidMove { ply, error } => write!(f, "{} at halfmove {}", error, ply),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Plays the main line through the move validator and checks the variations on the
+    /// way. Returns the starting position followed by the position after every move.
+    pub fn replay(&self) -> Result<Vec<GameState>, PgnError> {
+        let mut state = match self.tag("FEN") {
+            Some(fen) => GameState::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => GameState::new_with_default_position(),
+        };
+        let mut positions = vec![state.clone()];
+        for (index, pgn_move) in self.moves.iter().enumerate() {
+            let ply = index + 1;
+            for variation in &pgn_move.variations {
+                check_variation(&state, variation, ply)?;
+            }
+            play_pgn_move(&mut state, pgn_move, ply)?;
+            if !pgn_move.comments.is_empty()
+                && let Some(record) = state.move_history.last_mut()
+            {
+                record.comment = Some(pgn_move.comments.join(" "));
+            }
+            positions.push(state.clone());
+        }
+        Ok(positions)
+    }
+}
+
+/// Checks that the moves of a variation and of the variations inside it can be played.
+/// `state` is the position before the first move, which is at `first_ply` of the game.
+fn check_variation(state: &GameState, moves: &[PgnMove], first_ply: usize) -> Result<(), PgnError> {
+    let mut state = state.clone();
+    for (index, pgn_move) in moves.iter().enumerate() {
+        let ply = first_ply + index;
+        for variation in &pgn_move.variations {
+            check_variation(&state, variation, ply)?;
+        }
+        play_pgn_move(&mut state, pgn_move, ply)?;
+    }
+    Ok(())
+}
+
+fn play_pgn_move(state: &mut GameState, pgn_move: &PgnMove, ply: usize) -> Result<(), PgnError> {
+    state
+        .play_san(&pgn_move.san)
+        .map_err(|error| PgnError::InvalidMove { ply, error })
+}
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    /// A move, possibly with a move number or annotation suffix still attached
+    Symbol(String),
+}
+
+/// Whether the text starts like a PGN game, with a tag or a move number
+pub fn looks_like_pgn(text: &str) -> bool {
+    let text = text.trim_start();
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    text.starts_with('[') || (digits > 0 && text[digits..].starts_with('.'))
+}
+
+/// Parses every game in the PGN text
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the innermost line being parsed is at the end
+    let mut lines: Vec<Vec<PgnMove>> = vec![Vec::new()];
+    let mut has_content = false;
+
+    for token in tokenize(text)? {
+        match token {
+            Token::Tag(name, value) => {
+                // tags after movetext without a result belong to the next game
+                if lines.len() == 1 && !lines[0].is_empty() {
+                    game.moves = std::mem::take(&mut lines[0]);
+                    game.result = "*".to_string();
+                    games.push(std::mem::take(&mut game));
+                }
+                game.tags.push((name, value));
+                has_content = true;
+            }
+            Token::Comment(comment) => {
+                let depth = lines.len();
+                match lines.last_mut().and_then(|line| line.last_mut()) {
+                    Some(last) => last.comments.push(comment),
+                    None if depth == 1 => game.comments.push(comment),
+                    // a comment at the start of a variation, there is no move to attach it to
+                    None => {}
+                }
+                has_content = true;
+            }
+            Token::Nag(nag) => {
+                if let Some(last) = lines.last_mut().and_then(|line| line.last_mut()) {
+                    last.nags.push(nag);
+                }
+            }
+            Token::VariationStart => {
+                if lines.last().is_none_or(|line| line.is_empty()) {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                lines.push(Vec::new());
+            }
+            Token::VariationEnd => {
+                if lines.len() < 2 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                let variation = lines.pop().unwrap();
+                lines
+                    .last_mut()
+                    .and_then(|line| line.last_mut())
+                    .unwrap()
+                    .variations
+                    .push(variation);
+            }
+            Token::Result(result) => {
+                if lines.len() != 1 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                game.moves = std::mem::take(&mut lines[0]);
+                game.result = result;
+                games.push(std::mem::take(&mut game));
+                has_content = false;
+            }
+            // the optional en passant suffix, the move before it is complete already
+            Token::Symbol(symbol) if symbol == "e.p." => {}
+            Token::Symbol(symbol) => {
+                let (san, nags) = split_move_symbol(&symbol);
+                if !san.is_empty() {
+                    lines.last_mut().unwrap().push(PgnMove {
+                        san,
+                        nags,
+                        ..Default::default()
+                    });
+                    has_content = true;
+                }
+            }
+        }
+    }
+
+    if lines.len() != 1 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    if has_content {
+        game.moves = std::mem::take(&mut lines[0]);
+        game.result = "*".to_string();
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// Removes a leading move number like `12.` or `12...` and a trailing annotation like `!?`
+fn split_move_symbol(symbol: &str) -> (String, Vec<u8>) {
+    let digits = symbol.len()
+        - symbol
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+    let rest = &symbol[digits..];
+    let without_number = if digits > 0 && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else if digits > 0 && rest.is_empty() {
+        // a move number without a period
+        ""
+    } else {
+        // castling written with zeros also starts with a digit
+        symbol
+    };
+    let san = without_number.trim_end_matches(['!', '?']);
+    let nags = match &without_number[san.len()..] {
+        "!" => vec![1],
+        "?" => vec![2],
+        "!!" => vec![3],
+        "??" => vec![4],
+        "!?" => vec![5],
+        "?!" => vec![6],
+        _ => vec![],
+    };
+    (san.to_string(), nags)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    let mut line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        // a percent sign at the start of a line escapes the whole line
+        if line_start && c == '%' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = c == '\n';
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '[' => {
+                // an unterminated tag is reported with the rest of its line only
+                let end = tag_end(&chars, i).ok_or_else(|| {
+                    PgnError::InvalidTag(chars[i..].iter().take_while(|c| **c != '\n').collect())
+                })?;
+                let content: String = chars[i + 1..end].iter().collect();
+                tokens.push(parse_tag(&content)?);
+                i = end + 1;
+            }
+            '{' => {
+                let end = (i..chars.len())
+                    .find(|j| chars[*j] == '}')
+                    .ok_or(PgnError::UnterminatedComment)?;
+                let comment: String = chars[i + 1..end].iter().collect();
+                tokens.push(Token::Comment(
+                    comment.split_whitespace().collect::<Vec<_>>().join(" "),
+                ));
+                i = end + 1;
+            }
+            ';' => {
+                let end = (i..chars.len())
+                    .find(|j| chars[*j] == '\n')
+                    .unwrap_or(chars.len());
+                let comment: String = chars[i + 1..end].iter().collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+                i = end;
+            }
+            '(' => {
+                tokens.push(Token::VariationStart);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::VariationEnd);
+                i += 1;
+            }
+            '$' => {
+                let end = (i + 1..chars.len())
+                    .find(|j| !chars[*j].is_ascii_digit())
+                    .unwrap_or(chars.len());
+                let nag: String = chars[i..end].iter().collect();
+                let value = nag[1..]
+                    .parse::<u8>()
+                    .map_err(|_| PgnError::InvalidNag(nag.clone()))?;
+                tokens.push(Token::Nag(value));
+                i = end;
+            }
+            _ => {
+                let end = (i..chars.len())
+                    .find(|j| chars[*j].is_whitespace() || "[]{}();$".contains(chars[*j]))
+                    .unwrap_or(chars.len());
+                let symbol: String = chars[i..end].iter().collect();
+                tokens.push(match symbol.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => Token::Result(symbol),
+                    _ => Token::Symbol(symbol),
+                });
+                i = end;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Finds the closing bracket of the tag starting at `start`, skipping brackets in the quoted value
+fn tag_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if in_quotes => i += 1,
+            '"' => in_quotes = !in_quotes,
+            ']' if !in_quotes => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn parse_tag(content: &str) -> Result<Token, PgnError> {
+    let invalid = || PgnError::InvalidTag(content.to_string());
+    let content = content.trim();
+    let name_end = content.find(char::is_whitespace).ok_or_else(invalid)?;
+    let name = &content[..name_end];
+    let value = content[name_end..].trim();
+    if name.is_empty() || value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return Err(invalid());
+    }
+    let value = value[1..value.len() - 1]
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\");
+    Ok(Token::Tag(name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_one(text: &str) -> PgnGame {
+        let mut games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 1);
+        games.remove(0)
+    }
+
+    fn sans(moves: &[PgnMove]) -> Vec<&str> {
+        moves.iter().map(|m| m.san.as_str()).collect()
+    }
+
+    #[test]
+    fn tags_moves_and_result() {
+        let game =
+            parse_one("[Event \"Test\"]\n[White \"A\"]\n\n1. e4 e5 2. Nf3 {develops} Nc6 1-0");
+        assert_eq!(game.tag("Event"), Some("Test"));
+        assert_eq!(game.tag("White"), Some("A"));
+        assert_eq!(sans(&game.moves), ["e4", "e5", "Nf3", "Nc6"]);
+        assert_eq!(game.moves[2].comments, ["develops"]);
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.replay().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn en_passant_suffix_is_skipped() {
+        let game = parse_one("1. e4 Nf6 2. e5 d5 3. exd6 e.p. exd6 *");
+        assert_eq!(sans(&game.moves), ["e4", "Nf6", "e5", "d5", "exd6", "exd6"]);
+        assert!(game.replay().is_ok());
+    }
+
+    #[test]
+    fn variations_start_before_their_move() {
+        let game = parse_one("1. e4 (1. d4 d5 (1... Nf6 2. c4)) e5 (1... c5 2. Nf3) 2. Nf3 *");
+        assert_eq!(sans(&game.moves), ["e4", "e5", "Nf3"]);
+        assert_eq!(sans(&game.moves[0].variations[0]), ["d4", "d5"]);
+        assert_eq!(
+            sans(&game.moves[0].variations[0][1].variations[0]),
+            ["Nf6", "c4"]
+        );
+        assert_eq!(sans(&game.moves[1].variations[0]), ["c5", "Nf3"]);
+        assert!(game.replay().is_ok());
+    }
+
+    #[test]
+    fn illegal_moves_report_their_ply() {
+        let game = parse_one("1. e4 e5 2. Ke3 *");
+        assert_eq!(
+            game.replay().err(),
+            Some(PgnError::InvalidMove {
+                ply: 3,
+                error: SanError::IllegalMove("Ke3".to_string())
+            })
+        );
+
+        // the pawn on e5 is blocked by the one on e6 in the inner variation
+        let game = parse_one("1. e4 e5 (1... e6 2. d4 (2. e5 Qh4 3. e6) d5) 2. Nf3 *");
+        assert_eq!(
+            game.replay().err(),
+            Some(PgnError::InvalidMove {
+                ply: 5,
+                error: SanError::IllegalMove("e6".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn unbalanced_variations() {
+        for text in [
+            "1. e4 ) e5 *",
+            "( 1. e4 ) *",
+            "1. e4 (1. d4 *",
+            "1. e4 (1. d4",
+        ] {
+            assert_eq!(
+                parse_pgn(text).err(),
+                Some(PgnError::UnbalancedVariation),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        assert_eq!(
+            parse_pgn("1. e4 {never closed e5 *").err(),
+            Some(PgnError::UnterminatedComment)
+        );
+    }
+
+    #[test]
+    fn invalid_tags() {
+        assert_eq!(
+            parse_pgn("[Event \"Test\"\n1. e4 e5 *").err(),
+            Some(PgnError::InvalidTag("[Event \"Test\"".to_string()))
+        );
+        assert!(matches!(
+            parse_pgn("[Event]\n1. e4 *"),
+            Err(PgnError::InvalidTag(_))
+        ));
+    }
+}
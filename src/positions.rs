@@ -1,8 +1,68 @@
-use eframe::egui::Pos2;
+use eframe::egui::{Pos2, Rect, Vec2};
 
-use crate::{BOARD_SQUARE_SIZE, BOARD_SQUARES};
+use guh_chess::{
+    board::{Color, PieceType},
+    move_validation::validator::PROMOTION_PIECES,
+    square::{File, Rank, Square},
+};
 
-pub fn turn_info_text_position() -> Pos2 {
-    let board_size = (BOARD_SQUARES * BOARD_SQUARE_SIZE) as f32;
-    Pos2::new(25.0, board_size + 25.0)
+use crate::util::board_size;
+
+/// Maps a square to the place it is drawn on, as if white were at the bottom, and back.
+/// With black at the bottom the board is turned by 180 degrees, so the mapping is the
+/// same in both directions.
+pub fn oriented_square(square: Square, orientation: Color) -> Square {
+    match orientation {
+        Color::White => square,
+        Color::Black => square.rotated(),
+    }
+}
+
+/// The square under a position relative to the top left corner of the board,
+/// `None` outside of the board
+pub fn square_at(pos: Pos2, orientation: Color, square_size: f32) -> Option<Square> {
+    let column = (pos.x / square_size).floor();
+    let row = (pos.y / square_size).floor();
+    if column < 0.0 || row < 0.0 {
+        return None;
+    }
+    // the casts saturate, so far away positions end up beyond the edge as well
+    let file = File::A.offset(column as i8)?;
+    let rank = Rank::EIGHTH.offset(-(row as i8))?;
+    Some(oriented_square(Square::new(file, rank), orientation))
+}
+
+pub fn turn_info_text_position(square_size: f32) -> Pos2 {
+    let board_size = board_size(square_size);
+    Pos2::new(10.0, board_size + 18.0)
+}
+
+/// The clocks are right-aligned on the line of the turn info
+pub fn clock_text_position(square_size: f32) -> Pos2 {
+    let board_size = board_size(square_size);
+    Pos2::new(board_size - 10.0, board_size + 18.0)
+}
+
+/// The row of buttons below the turn info
+pub fn controls_rect(square_size: f32) -> Rect {
+    let board_size = board_size(square_size);
+    Rect::from_min_size(
+        Pos2::new(10.0, board_size + 36.0),
+        Vec2::new(board_size - 20.0, 34.0),
+    )
+}
+
+/// The squares of the promotion picker, starting at the promotion square and
+/// extending towards the center of the board
+pub fn promotion_picker_squares(destination: Square, color: Color) -> [(Square, PieceType); 4] {
+    let towards_center = -color.pawn_direction();
+    let mut squares = [(destination, PieceType::Queen); 4];
+    for (offset, piece_type) in PROMOTION_PIECES.into_iter().enumerate() {
+        // always on the board, the destination is on the last rank
+        let square = destination
+            .offset(0, towards_center * offset as i8)
+            .unwrap_or(destination);
+        squares[offset] = (square, piece_type);
+    }
+    squares
 }
@@ -1,22 +1,45 @@
+use std::time::{Duration, Instant};
+
 use eframe::egui::{
-    self, Align2, Color32, CornerRadius, FontId, ImageSource, Pos2, Rect, Ui, Vec2, ViewportCommand,
+    self, Align2, Color32, CornerRadius, FontId, ImageSource, Pos2, Rect, Shape, Stroke, Ui, Vec2,
+};
+
+use guh_chess::{
+    bitboard::squares,
+    board::{BoardExt, Color, Piece, PieceType},
+    engine::search::SearchInfo,
+    move_validation::validator::{find_king, generate_piece_map, is_in_check},
+    square::{File, Rank, Square},
+    state::GameState,
 };
 
 use crate::{
-    BOARD_SQUARE_SIZE, BOARD_SQUARES, ChessApp,
-    board::{Color, Piece, PieceType},
-    move_validation::validator::generate_piece_map,
-    positions::turn_info_text_position,
-    util::{board_size_vec2, viewport_size_vec2},
+    ChessApp,
+    annotations::Annotation,
+    positions::{
+        clock_text_position, oriented_square, promotion_picker_squares, turn_info_text_position,
+    },
+    util::board_size_vec2,
 };
 
-pub fn resize(ctx: &egui::Context) {
-    ctx.send_viewport_cmd(ViewportCommand::InnerSize(viewport_size_vec2()));
-}
+/// How many moves of the engine's best line are shown while analysing
+const ANALYSIS_LINE_MOVES: usize = 5;
+
+/// The size of the pieces in the captured pieces trays
+const CAPTURED_PIECE_SIZE: f32 = 20.0;
+
+/// Drawn over the square of a king in check
+const CHECK_COLOR: Color32 = Color32::from_rgba_premultiplied(160, 0, 0, 160);
+/// The color of the arrows and circles drawn with the right mouse button
+const ANNOTATION_COLOR: Color32 = Color32::from_rgba_premultiplied(0, 100, 20, 150);
 
 pub fn render(app: &ChessApp, ui: &mut Ui, painter: &mut egui::Painter) {
     render_board_squares(app, painter);
+    render_coordinates(app, painter);
     render_pieces(app, ui);
+    render_annotations(app, painter);
+    render_promotion_picker(app, ui, painter);
+    render_dragged_piece(app, ui);
     render_info(app, painter);
 }
 
@@ -24,77 +47,230 @@ fn render_board_squares(app: &ChessApp, painter: &mut egui::Painter) {
     painter.rect_filled(
         Rect {
             min: Pos2::ZERO,
-            max: board_size_vec2().to_pos2(),
+            max: board_size_vec2(app.square_size).to_pos2(),
         },
         0,
-        Color32::DARK_GRAY,
+        app.theme().dark_square,
     );
-    let mut use_white: bool = true;
-    for i in 0..BOARD_SQUARES {
-        for j in 0..BOARD_SQUARES {
-            if use_white {
-                painter.rect_filled(
-                    make_rect_for_index(i * BOARD_SQUARES + j),
-                    CornerRadius::ZERO,
-                    Color32::WHITE,
-                );
-            }
-            use_white = !use_white;
+    for square in Square::all().filter(|square| !square.is_dark()) {
+        painter.rect_filled(
+            make_rect_for_square(square, Color::White, app.square_size),
+            CornerRadius::ZERO,
+            app.theme().light_square,
+        );
+    }
+    let state = app.displayed_state();
+    if let Some(last_move) = state.move_history.last() {
+        for square in [last_move.start, last_move.destination] {
+            painter.rect_filled(
+                make_rect_for_square(square, app.orientation(), app.square_size),
+                CornerRadius::ZERO,
+                app.theme().last_move,
+            );
         }
-        use_white = !use_white;
     }
-    if let Some(selected_square) = app.state.selected_square {
+    if is_in_check(&state.board, state.turn)
+        && let Some(king) = find_king(&state.board, state.turn)
+    {
+        painter.rect_filled(
+            make_rect_for_square(king, app.orientation(), app.square_size),
+            CornerRadius::ZERO,
+            CHECK_COLOR,
+        );
+    }
+    if let Some(selected_square) = state.selected_square {
         painter.rect_filled(
-            make_rect_for_index(selected_square),
+            make_rect_for_square(selected_square, app.orientation(), app.square_size),
             CornerRadius::ZERO,
-            Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+            app.theme().selected,
         );
         let possible_squares = generate_piece_map(
-            &app.state.board,
-            &app.state.additional_board_data,
-            app.state.turn,
+            &state.board,
+            &state.additional_board_data,
+            state.turn,
             selected_square,
         );
         for sq in possible_squares {
             painter.rect_filled(
-                make_rect_for_index(sq),
+                make_rect_for_square(sq, app.orientation(), app.square_size),
                 CornerRadius::ZERO,
-                Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+                app.theme().legal_move,
             );
         }
     };
 }
 
+/// Files along the bottom edge and ranks along the left edge, in the color of the
+/// other squares so they stay readable
+fn render_coordinates(app: &ChessApp, painter: &mut egui::Painter) {
+    if !app.show_coordinates {
+        return;
+    }
+    let orientation = app.orientation();
+    let label_color = |square: Square| {
+        if square.is_dark() {
+            app.theme().light_square
+        } else {
+            app.theme().dark_square
+        }
+    };
+    for file in File::all() {
+        let square = oriented_square(Square::new(file, Rank::FIRST), orientation);
+        let rect = make_rect_for_square(square, orientation, app.square_size);
+        painter.text(
+            rect.right_bottom() + Vec2::new(-3.0, -1.0),
+            Align2::RIGHT_BOTTOM,
+            square.file().to_string(),
+            FontId::proportional(app.square_size * 0.22),
+            label_color(square),
+        );
+    }
+    for rank in Rank::all() {
+        let square = oriented_square(Square::new(File::A, rank), orientation);
+        let rect = make_rect_for_square(square, orientation, app.square_size);
+        painter.text(
+            rect.left_top() + Vec2::new(3.0, 1.0),
+            Align2::LEFT_TOP,
+            square.rank().to_string(),
+            FontId::proportional(app.square_size * 0.22),
+            label_color(square),
+        );
+    }
+}
+
 fn render_pieces(app: &ChessApp, ui: &mut Ui) {
-    for (y, row) in app.state.board.chunks(BOARD_SQUARES as usize).enumerate() {
-        for (x, piece) in row.iter().enumerate() {
-            if let Some(piece) = piece {
-                render_piece_at(piece, (x as u16, y as u16), ui);
+    let board = &app.displayed_state().board;
+    let dragged = app.dragging.map(|(square, _)| square);
+    for square in squares(board.occupied()) {
+        if Some(square) == dragged {
+            continue;
+        }
+        if let Some(piece) = board.piece_at(square) {
+            render_piece_at(&piece, square, app.orientation(), app.square_size, ui);
+        }
+    }
+}
+
+/// Arrows and circles are drawn above the pieces so they stay visible
+fn render_annotations(app: &ChessApp, painter: &mut egui::Painter) {
+    let width = app.square_size * 0.12;
+    let center = |square| make_rect_for_square(square, app.orientation(), app.square_size).center();
+    for annotation in &app.annotations {
+        match *annotation {
+            Annotation::Circle(square) => {
+                painter.circle_stroke(
+                    center(square),
+                    (app.square_size - width) / 2.0,
+                    Stroke::new(width, ANNOTATION_COLOR),
+                );
+            }
+            Annotation::Arrow(start, end) => {
+                paint_arrow(center(start), center(end), width, app.square_size, painter);
             }
         }
     }
 }
 
-fn render_piece_at(piece: &Piece, position: (u16, u16), ui: &mut Ui) {
+/// A line from `from` with a triangular head ending at `to`
+fn paint_arrow(from: Pos2, to: Pos2, width: f32, square_size: f32, painter: &egui::Painter) {
+    let direction = (to - from).normalized();
+    let head_length = square_size * 0.4;
+    let head_base = to - direction * head_length;
+    let head_side = direction.rot90() * head_length * 0.6;
+    painter.line_segment([from, head_base], Stroke::new(width, ANNOTATION_COLOR));
+    painter.add(Shape::convex_polygon(
+        vec![to, head_base + head_side, head_base - head_side],
+        ANNOTATION_COLOR,
+        Stroke::NONE,
+    ));
+}
+
+fn render_promotion_picker(app: &ChessApp, ui: &mut Ui, painter: &mut egui::Painter) {
+    if let Some((_, destination)) = app.pending_promotion {
+        painter.rect_filled(
+            Rect {
+                min: Pos2::ZERO,
+                max: board_size_vec2(app.square_size).to_pos2(),
+            },
+            0,
+            Color32::from_rgba_unmultiplied(0, 0, 0, 160),
+        );
+        for (square, piece_type) in
+            promotion_picker_squares(destination, app.displayed_state().turn)
+        {
+            painter.rect_filled(
+                make_rect_for_square(square, app.orientation(), app.square_size),
+                CornerRadius::same(8),
+                Color32::LIGHT_GRAY,
+            );
+            render_piece_at(
+                &Piece::new(piece_type, app.displayed_state().turn),
+                square,
+                app.orientation(),
+                app.square_size,
+                ui,
+            );
+        }
+    }
+}
+
+/// The dragged piece is drawn last, centered on the cursor above everything else
+fn render_dragged_piece(app: &ChessApp, ui: &mut Ui) {
+    let Some((square, pos)) = app.dragging else {
+        return;
+    };
+    if let Some(piece) = app.displayed_state().board.piece_at(square) {
+        paint_piece(
+            &piece,
+            Rect::from_center_size(pos, Vec2::splat(app.square_size)),
+            ui,
+        );
+    }
+}
+
+/// The pieces `capturer` has taken, followed by its material advantage if it is ahead
+pub fn render_captured_pieces(state: &GameState, capturer: Color, ui: &mut Ui) {
+    let balance = match capturer {
+        Color::White => state.material_balance(),
+        Color::Black => -state.material_balance(),
+    };
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        ui.set_min_height(CAPTURED_PIECE_SIZE);
+        for piece in state.captured_pieces(capturer.opposite()) {
+            ui.add(
+                egui::Image::new(get_piece_image(&piece))
+                    .fit_to_exact_size(Vec2::splat(CAPTURED_PIECE_SIZE)),
+            );
+        }
+        if balance > 0 {
+            ui.label(format!(" +{}", balance));
+        }
+    });
+}
+
+fn render_piece_at(
+    piece: &Piece,
+    square: Square,
+    orientation: Color,
+    square_size: f32,
+    ui: &mut Ui,
+) {
+    paint_piece(
+        piece,
+        make_rect_for_square(square, orientation, square_size),
+        ui,
+    );
+}
+
+fn paint_piece(piece: &Piece, rect: Rect, ui: &mut Ui) {
     egui::Image::new(get_piece_image(piece))
-        .max_width(BOARD_SQUARE_SIZE as f32)
+        .max_width(rect.width())
         .alt_text(format!(
             "Failed to render image of piece type={} color={} at {} {}",
-            piece.piece_type as i8, piece.color as i8, position.0, position.1
+            piece.piece_type as i8, piece.color as i8, rect.min.x, rect.min.y
         ))
-        .paint_at(
-            ui,
-            Rect {
-                min: Pos2 {
-                    x: (BOARD_SQUARE_SIZE * position.0) as f32,
-                    y: (BOARD_SQUARE_SIZE * position.1) as f32,
-                },
-                max: Pos2 {
-                    x: (BOARD_SQUARE_SIZE * (position.0 + 1)) as f32,
-                    y: (BOARD_SQUARE_SIZE * (position.1 + 1)) as f32,
-                },
-            },
-        );
+        .paint_at(ui, rect);
 }
 
 fn get_piece_image(piece: &Piece) -> ImageSource<'static> {
@@ -126,28 +302,149 @@ fn get_piece_image(piece: &Piece) -> ImageSource<'static> {
     }
 }
 
-fn make_rect_for_index(index: u16) -> Rect {
+/// The rect the square is drawn in, seen from the side at the bottom
+fn make_rect_for_square(square: Square, orientation: Color, square_size: f32) -> Rect {
+    let square = oriented_square(square, orientation);
     let pos: (f32, f32) = (
-        ((index % BOARD_SQUARES) * BOARD_SQUARE_SIZE) as f32,
-        ((index / BOARD_SQUARES) * BOARD_SQUARE_SIZE) as f32,
+        square.file().index() as f32 * square_size,
+        (Rank::EIGHTH.index() - square.rank().index()) as f32 * square_size,
     );
-    Rect::from_min_size(Pos2::from(pos), Vec2::splat(BOARD_SQUARE_SIZE as f32))
+    Rect::from_min_size(Pos2::from(pos), Vec2::splat(square_size))
 }
 
 fn render_info(app: &ChessApp, painter: &mut egui::Painter) {
+    let text = if let Some(message) = &app.message {
+        message.clone()
+    } else if let Some(replay) = &app.replay {
+        let players = replay.current_game().map_or(String::new(), |game| {
+            format!(
+                "{} - {}",
+                game.tag("White").unwrap_or("?"),
+                game.tag("Black").unwrap_or("?")
+            )
+        });
+        format!(
+            "Game {}/{}, move {}/{} {}",
+            replay.game_index + 1,
+            replay.games.len().max(1),
+            replay.position_index,
+            replay.positions.len() - 1,
+            players
+        )
+    } else if let Some((moves, _)) = &app.viewed_position {
+        format!(
+            "Move {}/{}, click the board to return",
+            moves,
+            app.state.move_history.len()
+        )
+    } else if app.displayed_state().result.is_over() {
+        app.displayed_state().result.to_string()
+    } else if let Some(search) = &app.computer_search {
+        thinking_text(app, search.latest_info().as_ref())
+    } else if let Some(engine) = app
+        .external_engine
+        .as_ref()
+        .filter(|_| app.external_engine_playing)
+    {
+        thinking_text(app, engine.latest_info())
+    } else if let Some(engine) = app.external_engine.as_ref().filter(|_| app.analysis) {
+        engine
+            .latest_info()
+            .map_or("Analysing...".to_string(), |info| {
+                analysis_text(app.displayed_state(), info)
+            })
+    } else if app.displayed_state().claimable_draw().is_some() {
+        format!(
+            "{} to move, D: claim draw",
+            color_name(app.displayed_state().turn)
+        )
+    } else {
+        format!("{} to move", color_name(app.displayed_state().turn))
+    };
     painter.text(
-        turn_info_text_position(),
+        turn_info_text_position(app.square_size),
         Align2::LEFT_CENTER,
-        format!(
-            "{} to move",
-            if app.state.turn == Color::White {
-                "White"
-            } else {
-                "Black"
-            }
-        ),
+        text,
         FontId::monospace(15.0),
         Color32::WHITE,
     );
     //println!("Text render pos: {}", pos);
+    render_clocks(app, painter);
+}
+
+fn render_clocks(app: &ChessApp, painter: &mut egui::Painter) {
+    let Some(clock) = &app.displayed_state().clock else {
+        return;
+    };
+    let now = Instant::now();
+    let text = format!(
+        "W {}  B {}",
+        format_clock_time(clock.remaining(Color::White, now)),
+        format_clock_time(clock.remaining(Color::Black, now))
+    );
+    painter.text(
+        clock_text_position(app.square_size),
+        Align2::RIGHT_CENTER,
+        text,
+        FontId::monospace(15.0),
+        Color32::WHITE,
+    );
+}
+
+/// Minutes and seconds, with tenths of a second once less than ten seconds are left
+fn format_clock_time(time: Duration) -> String {
+    if time < Duration::from_secs(10) {
+        format!("{}.{}", time.as_secs(), time.subsec_millis() / 100)
+    } else {
+        let seconds = time.as_secs();
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn thinking_text(app: &ChessApp, info: Option<&SearchInfo>) -> String {
+    let progress = info.map_or(String::new(), |info| {
+        format!(", depth {} {}", info.depth, score_text(info, 1))
+    });
+    format!(
+        "{} is thinking{}",
+        color_name(app.displayed_state().turn),
+        progress
+    )
+}
+
+/// The depth, the score from white's point of view and the start of the best line in SAN
+fn analysis_text(state: &GameState, info: &SearchInfo) -> String {
+    let perspective = if state.turn == Color::White { 1 } else { -1 };
+    let mut line = state.clone();
+    for m in info.pv.iter().take(ANALYSIS_LINE_MOVES) {
+        if !line.move_piece(m.start, m.destination, m.promotion) {
+            break;
+        }
+    }
+    let moves: Vec<&str> = line.move_history[state.move_history.len()..]
+        .iter()
+        .map(|record| record.san.as_str())
+        .collect();
+    format!(
+        "d{} {} {}",
+        info.depth,
+        score_text(info, perspective),
+        moves.join(" ")
+    )
+}
+
+/// The score in pawns or the moves to mate, multiplied by `perspective` to turn it around
+fn score_text(info: &SearchInfo, perspective: i32) -> String {
+    match info.mate_in() {
+        Some(moves) => format!("#{}", moves * perspective),
+        None => format!("{:+.2}", (info.score * perspective) as f32 / 100.0),
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    if color == Color::White {
+        "White"
+    } else {
+        "Black"
+    }
 }
@@ -0,0 +1,65 @@
+use crate::{
+    pgn::{PgnError, PgnGame},
+    state::GameState,
+};
+
+/// Steps through the positions of games loaded from a PGN file
+pub struct Replay {
+    pub games: Vec<PgnGame>,
+    pub game_index: usize,
+    /// The starting position followed by the position after every move of the current game
+    pub positions: Vec<GameState>,
+    pub position_index: usize,
+}
+
+impl Replay {
+    /// Starts the replay at the first game, or at the default position if there are no
+    /// games. Fails if the first game contains an illegal move.
+    pub fn new(games: Vec<PgnGame>) -> Result<Self, PgnError> {
+        let first = games.first().map_or_else(
+            || Ok(vec![GameState::new_with_default_position()]),
+            |game| game.replay(),
+        )?;
+        Ok(Self {
+            games,
+            game_index: 0,
+            positions: first,
+            position_index: 0,
+        })
+    }
+
+    /// Switches to another game of the file, starting at its first position.
+    /// The current game stays loaded if the other one contains an illegal move.
+    pub fn load_game(&mut self, game_index: usize) -> Result<(), PgnError> {
+        if let Some(game) = self.games.get(game_index) {
+            self.positions = game.replay()?;
+            self.game_index = game_index;
+            self.position_index = 0;
+        }
+        Ok(())
+    }
+
+    pub fn current(&self) -> &GameState {
+        &self.positions[self.position_index]
+    }
+
+    pub fn current_game(&self) -> Option<&PgnGame> {
+        self.games.get(self.game_index)
+    }
+
+    pub fn first(&mut self) {
+        self.position_index = 0;
+    }
+
+    pub fn previous(&mut self) {
+        self.position_index = self.position_index.saturating_sub(1);
+    }
+
+    pub fn next(&mut self) {
+        self.position_index = (self.position_index + 1).min(self.positions.len() - 1);
+    }
+
+    pub fn last(&mut self) {
+        self.position_index = self.positions.len() - 1;
+    }
+}
@@ -0,0 +1,382 @@
+use std::fmt::Display;
+
+use crate::{
+    board::{BoardExt, PieceType},
+    move_validation::{
+        movement::Movement,
+        validator::{generate_piece_map, has_legal_moves, is_in_check, play_move},
+    },
+    square::{File, Rank, Square},
+    state::GameState,
+};
+
+/// Converts a legal move into Standard Algebraic Notation, e.g. `Nbd7`, `exd6`, `e8=Q+` or `O-O-O#`
+pub fn move_to_san(m: &Movement) -> String {
+    let info = &m.movement_info;
+    let mut san = String::new();
+
+    if info.piece_type == PieceType::King && m.start.file().distance(m.destination.file()) == 2 {
+        san.push_str(if m.destination.file() > m.start.file() {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        if info.piece_type == PieceType::Pawn {
+            // pawn captures are identified by the file the pawn came from
+            if info.capturing {
+                san.push(m.start.file().to_char());
+            }
+        } else {
+            san.push(piece_letter(info.piece_type));
+            san.push_str(&disambiguation(m));
+        }
+        if info.capturing {
+            san.push('x');
+        }
+        san.push_str(&m.destination.to_string());
+        if let Some(promotion) = m.promotion {
+            san.push('=');
+            san.push(piece_letter(promotion));
+        }
+    }
+
+    let mut board = info.board;
+    let mut board_data = info.board_data;
+    play_move(
+        &mut board,
+        &mut board_data,
+        m.start,
+        m.destination,
+        m.promotion,
+    );
+    let opponent = info.piece_color.opposite();
+    if is_in_check(&board, opponent) {
+        san.push(if has_legal_moves(&board, &board_data, opponent) {
+            '+'
+        } else {
+            '#'
+        });
+    }
+    san
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanError {
+    /// The text is not a move in algebraic notation
+    Malformed(String),
+    /// The move is well formed but not legal in the position
+    IllegalMove(String),
+    /// More than one legal move matches, the start square has to be specified
+    AmbiguousMove(String),
+    /// The game is already over, so no move can be played
+    GameOver,
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "'{}' is not a valid move", san),
+            SanError::IllegalMove(san) => write!(f, "'{}' is not a legal move", san),
+            SanError::AmbiguousMove(san) => write!(f, "'{}' is ambiguous", san),
+            SanError::GameOver => write!(f, "the game is over"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+/// What a SAN string says about a move, before looking at the position
+struct SanPattern {
+    piece_type: PieceType,
+    /// Only set for castling, which is matched by the king's destination file
+    castling_kingside: Option<bool>,
+    start_file: Option<File>,
+    start_rank: Option<Rank>,
+    destination: Option<Square>,
+    promotion: Option<PieceType>,
+}
+
+/// Finds the legal move described by the SAN string in the current position of the game.
+/// Check and annotation suffixes like `+`, `#`, `!` or `?` are ignored, castling may be written
+/// with zeros, and over-specified start squares (`Ngf3`) or a missing `=` before the promotion
+/// piece (`e8Q`) are accepted.
+pub fn parse_san(state: &GameState, san: &str) -> Result<Movement, SanError> {
+    let pattern = parse_san_pattern(san).ok_or_else(|| SanError::Malformed(san.to_string()))?;
+    let mut matching: Vec<Movement> = state
+        .legal_moves()
+        .into_iter()
+        .filter(|m| pattern.matches(m))
+        .collect();
+    match matching.len() {
+        0 => Err(SanError::IllegalMove(san.to_string())),
+        1 => Ok(matching.remove(0)),
+        _ => Err(SanError::AmbiguousMove(san.to_string())),
+    }
+}
+
+fn parse_san_pattern(san: &str) -> Option<SanPattern> {
+    let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    match text {
+        "O-O" | "0-0" => return Some(SanPattern::castling(true)),
+        "O-O-O" | "0-0-0" => return Some(SanPattern::castling(false)),
+        _ => {}
+    }
+
+    let mut chars: Vec<char> = text.chars().filter(|c| *c != 'x' && *c != ':').collect();
+    let piece_type = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => {
+            let piece_type = piece_type_from_letter(*c)?;
+            chars.remove(0);
+            piece_type
+        }
+        _ => PieceType::Pawn,
+    };
+
+    // the promotion piece follows the destination rank, written as `=Q`, `Q` or `(Q)`
+    let mut promotion = None;
+    if piece_type == PieceType::Pawn {
+        if chars.last() == Some(&')') {
+            chars.pop();
+        }
+        let promotion_piece = chars
+            .last()
+            .and_then(|c| piece_type_from_letter(c.to_ascii_uppercase()));
+        if let Some(promotion_piece) = promotion_piece {
+            chars.pop();
+            if matches!(chars.last(), Some('=') | Some('(')) {
+                chars.pop();
+            }
+            if !chars.last().is_some_and(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            promotion = Some(promotion_piece);
+        }
+    }
+
+    if chars.len() < 2 || chars.len() > 4 {
+        return None;
+    }
+    let destination_name: String = chars[chars.len() - 2..].iter().collect();
+    let destination = Square::from_algebraic(&destination_name)?;
+    let mut start_file = None;
+    let mut start_rank = None;
+    for c in &chars[..chars.len() - 2] {
+        match (File::from_char(*c), Rank::from_char(*c)) {
+            (Some(file), _) if start_file.is_none() && start_rank.is_none() => {
+                start_file = Some(file)
+            }
+            (_, Some(rank)) if start_rank.is_none() => start_rank = Some(rank),
+            _ => return None,
+        }
+    }
+
+    Some(SanPattern {
+        piece_type,
+        castling_kingside: None,
+        start_file,
+        start_rank,
+        destination: Some(destination),
+        promotion,
+    })
+}
+
+impl SanPattern {
+    fn castling(kingside: bool) -> Self {
+        Self {
+            piece_type: PieceType::King,
+            castling_kingside: Some(kingside),
+            start_file: None,
+            start_rank: None,
+            destination: None,
+            promotion: None,
+        }
+    }
+
+    fn matches(&self, m: &Movement) -> bool {
+        let info = &m.movement_info;
+        if info.piece_type != self.piece_type || m.promotion != self.promotion {
+            return false;
+        }
+        let is_castling = info.piece_type == PieceType::King
+            && m.start.file().distance(m.destination.file()) == 2;
+        match self.castling_kingside {
+            Some(kingside) => is_castling && (m.destination.file() > m.start.file()) == kingside,
+            None => {
+                !is_castling
+                    && self.destination == Some(m.destination)
+                    && self.start_file.is_none_or(|file| file == m.start.file())
+                    && self.start_rank.is_none_or(|rank| rank == m.start.rank())
+            }
+        }
+    }
+}
+
+fn piece_type_from_letter(c: char) -> Option<PieceType> {
+    match c {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+/// The uppercase letter used for a piece type in SAN. Pawns have no letter in SAN,
+/// but `P` is returned for completeness.
+pub fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+/// Returns the file, rank or full square of the start square if another piece of the same
+/// type could also move to the destination
+fn disambiguation(m: &Movement) -> String {
+    let info = &m.movement_info;
+    let others: Vec<Square> = Square::all()
+        .filter(|square| {
+            *square != m.start
+                && info
+                    .board
+                    .piece_at(*square)
+                    .is_some_and(|p| p.piece_type == info.piece_type && p.color == info.piece_color)
+                && generate_piece_map(&info.board, &info.board_data, info.turn, *square)
+                    .contains(&m.destination)
+        })
+        .collect();
+
+    if others.is_empty() {
+        String::new()
+    } else if others.iter().all(|square| square.file() != m.start.file()) {
+        m.start.file().to_string()
+    } else if others.iter().all(|square| square.rank() != m.start.rank()) {
+        m.start.rank().to_string()
+    } else {
+        m.start.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SAN of the move from `start` to `destination` in the position
+    fn san(fen: &str, start: &str, destination: &str, promotion: Option<PieceType>) -> String {
+        let state = GameState::from_fen(fen).unwrap();
+        let start = Square::from_algebraic(start).unwrap();
+        let destination = Square::from_algebraic(destination).unwrap();
+        let m = state
+            .legal_moves()
+            .into_iter()
+            .find(|m| m.start == start && m.destination == destination && m.promotion == promotion)
+            .expect("the move should be legal");
+        move_to_san(&m)
+    }
+
+    fn parse(fen: &str, san: &str) -> Result<(Square, Square, Option<PieceType>), SanError> {
+        let state = GameState::from_fen(fen).unwrap();
+        parse_san(&state, san).map(|m| (m.start, m.destination, m.promotion))
+    }
+
+    fn square(name: &str) -> Square {
+        Square::from_algebraic(name).unwrap()
+    }
+
+    const KNIGHTS: &str = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+    const ROOKS: &str = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+    const QUEENS: &str = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+    const PROMOTION: &str = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+    const CASTLING: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn pawn_moves_and_captures() {
+        let fen = "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "e4", "e5", None), "e5");
+        assert_eq!(san(fen, "e4", "d5", None), "exd5");
+    }
+
+    #[test]
+    fn disambiguation() {
+        assert_eq!(san(KNIGHTS, "b1", "d2", None), "Nbd2");
+        assert_eq!(san(KNIGHTS, "b1", "c3", None), "Nc3");
+        assert_eq!(san(ROOKS, "a1", "a3", None), "R1a3");
+        assert_eq!(san(ROOKS, "a5", "a3", None), "R5a3");
+        assert_eq!(san(QUEENS, "a1", "b2", None), "Qa1b2");
+    }
+
+    #[test]
+    fn promotion_check_and_mate() {
+        assert_eq!(san(PROMOTION, "a7", "a8", Some(PieceType::Queen)), "a8=Q+");
+        assert_eq!(san(PROMOTION, "a7", "a8", Some(PieceType::Knight)), "a8=N");
+        assert_eq!(
+            san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1", "a8", None),
+            "Ra8#"
+        );
+    }
+
+    #[test]
+    fn castling() {
+        assert_eq!(san(CASTLING, "e1", "g1", None), "O-O");
+        assert_eq!(san(CASTLING, "e1", "c1", None), "O-O-O");
+        assert_eq!(
+            parse(CASTLING, "0-0"),
+            Ok((square("e1"), square("g1"), None))
+        );
+        assert_eq!(
+            parse(CASTLING, "O-O-O"),
+            Ok((square("e1"), square("c1"), None))
+        );
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        assert_eq!(
+            parse(KNIGHTS, "Nbd2"),
+            Ok((square("b1"), square("d2"), None))
+        );
+        assert_eq!(
+            parse(KNIGHTS, "Nb1d2!?"),
+            Ok((square("b1"), square("d2"), None))
+        );
+        assert_eq!(
+            parse(PROMOTION, "a8Q+"),
+            Ok((square("a7"), square("a8"), Some(PieceType::Queen)))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse(KNIGHTS, "Nd2"),
+            Err(SanError::AmbiguousMove("Nd2".to_string()))
+        );
+        assert_eq!(
+            parse(QUEENS, "Qab2"),
+            Err(SanError::AmbiguousMove("Qab2".to_string()))
+        );
+        assert_eq!(
+            parse(KNIGHTS, "Nd4"),
+            Err(SanError::IllegalMove("Nd4".to_string()))
+        );
+        assert_eq!(
+            parse(KNIGHTS, "hello"),
+            Err(SanError::Malformed("hello".to_string()))
+        );
+    }
+
+    #[test]
+    fn no_moves_after_the_game_ended() {
+        let mut state = GameState::new_with_default_position();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            state.play_san(san).unwrap();
+        }
+        assert_eq!(state.play_san("a3"), Err(SanError::GameOver));
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::Display;
+
+use crate::board::Color;
+
+/// A column of the board, from a to h
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct File(u8);
+
+impl File {
+    pub const A: File = File(0);
+    pub const B: File = File(1);
+    pub const C: File = File(2);
+    pub const D: File = File(3);
+    pub const E: File = File(4);
+    pub const F: File = File(5);
+    pub const G: File = File(6);
+    pub const H: File = File(7);
+
+    /// The file with the index, 0 for a up to 7 for h
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 8 { Some(File(index)) } else { None }
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// The file `files` towards the h-file, `None` beyond the edge of the board
+    pub fn offset(self, files: i8) -> Option<Self> {
+        self.0.checked_add_signed(files).and_then(File::new)
+    }
+
+    /// How many files apart the two files are
+    pub fn distance(self, other: File) -> u8 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// Parses the letter of the file, `a` to `h`
+    pub fn from_char(c: char) -> Option<Self> {
+        if c.is_ascii_lowercase() {
+            File::new(c as u8 - b'a')
+        } else {
+            None
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'a' + self.0) as char
+    }
+
+    /// All files from a to h
+    pub fn all() -> impl DoubleEndedIterator<Item = File> {
+        (0..8).map(File)
+    }
+}
+
+impl Display for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// A row of the board, from the first rank on white's side to the eighth on black's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rank(u8);
+
+impl Rank {
+    pub const FIRST: Rank = Rank(0);
+    pub const SECOND: Rank = Rank(1);
+    pub const THIRD: Rank = Rank(2);
+    pub const FOURTH: Rank = Rank(3);
+    pub const FIFTH: Rank = Rank(4);
+    pub const SIXTH: Rank = Rank(5);
+    pub const SEVENTH: Rank = Rank(6);
+    pub const EIGHTH: Rank = Rank(7);
+
+    /// The rank with the index, 0 for the first rank up to 7 for the eighth
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 8 { Some(Rank(index)) } else { None }
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// The rank `ranks` towards the eighth rank, `None` beyond the edge of the board
+    pub fn offset(self, ranks: i8) -> Option<Self> {
+        self.0.checked_add_signed(ranks).and_then(Rank::new)
+    }
+
+    /// How many ranks apart the two ranks are
+    pub fn distance(self, other: Rank) -> u8 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// The rank as counted from the side of `color`, so `Rank::FIRST.relative_to(color)`
+    /// is the home rank of `color`
+    pub fn relative_to(self, color: Color) -> Rank {
+        match color {
+            Color::White => self,
+            Color::Black => Rank(7 - self.0),
+        }
+    }
+
+    /// Parses the digit of the rank, `1` to `8`
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '1'..='8' => Rank::new(c as u8 - b'1'),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'1' + self.0) as char
+    }
+
+    /// All ranks from the first to the eighth
+    pub fn all() -> impl DoubleEndedIterator<Item = Rank> {
+        (0..8).map(Rank)
+    }
+}
+
+impl Display for Rank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// A square of the board. Squares are numbered row by row as the board is drawn with
+/// white at the bottom, from a8 as 0 to h1 as 63, which is also the bit of the square
+/// in a `Bitboard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+impl Square {
+    pub const fn new(file: File, rank: Rank) -> Self {
+        Square((7 - rank.0) * 8 + file.0)
+    }
+
+    /// The square with the index, see `Square`
+    pub const fn from_index(index: u8) -> Option<Self> {
+        if index < 64 {
+            Some(Square(index))
+        } else {
+            None
+        }
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub const fn file(self) -> File {
+        File(self.0 % 8)
+    }
+
+    pub const fn rank(self) -> Rank {
+        Rank(7 - self.0 / 8)
+    }
+
+    /// The square `files` towards the h-file and `ranks` towards the eighth rank,
+    /// `None` if that is off the board
+    pub fn offset(self, files: i8, ranks: i8) -> Option<Self> {
+        Some(Square::new(
+            self.file().offset(files)?,
+            self.rank().offset(ranks)?,
+        ))
+    }
+
+    /// The square in the same place when the board is turned by 180 degrees
+    pub const fn rotated(self) -> Self {
+        Square(63 - self.0)
+    }
+
+    /// The square on the same file with the rank counted from black's side,
+    /// e.g. e2 for e7
+    pub const fn mirrored(self) -> Self {
+        Square(self.0 ^ 56)
+    }
+
+    /// `true` for a1, h8 and the other dark squares
+    pub const fn is_dark(self) -> bool {
+        (self.0 / 8 + self.0 % 8) % 2 == 1
+    }
+
+    /// Parses a square name like `e4`
+    pub fn from_algebraic(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        let file = File::from_char(chars.next()?)?;
+        let rank = Rank::from_char(chars.next()?)?;
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(Square::new(file, rank))
+    }
+
+    /// All squares in the order of their index, from a8 along the eighth rank to h1
+    pub fn all() -> impl DoubleEndedIterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+/// The square name like `e4`
+impl Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
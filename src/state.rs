@@ -1,60 +1,253 @@
+use std::time::Instant;
+
 use crate::{
-    board::{Board, BoardExt, BoardIndex, BoardIndexExt, BoardIndexXYExt, Color, PieceType},
-    move_validation::{movement::Movement, validator::validate_move},
+    board::{Board, BoardExt, Color, PIECE_TYPES, Piece, PieceType},
+    clock::{ChessClock, TimeControl},
+    draw_rules::{
+        FIFTY_MOVE_RULE_HALFMOVES, PositionKey, SEVENTY_FIVE_MOVE_RULE_HALFMOVES, can_checkmate,
+        is_insufficient_material,
+    },
+    fen::{FenError, parse_fen, to_fen},
+    game_result::{DrawReason, GameResult, WinReason},
+    move_validation::{
+        movement::Movement,
+        validator::{
+            castling_rook_squares, en_passant_capture_square, generate_legal_moves,
+            generate_piece_map, has_legal_moves, is_in_check, is_last_row, play_move,
+            validate_move,
+        },
+    },
+    san::{SanError, move_to_san, parse_san},
+    square::{File, Rank, Square},
 };
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct GameState {
     pub board: Board,
-    pub selected_square: Option<BoardIndex>,
+    pub selected_square: Option<Square>,
     pub turn: Color,
     pub additional_board_data: AdditionalBoardData,
+    pub result: GameResult,
+    /// Every position of the game so far, including the current one
+    pub position_history: Vec<PositionKey>,
+    /// Every move played so far, in order
+    pub move_history: Vec<MoveRecord>,
+    /// Moves taken back with `undo`, the next one to redo is at the end
+    pub redo_stack: Vec<MoveRecord>,
+    /// The FEN of the position the game started from
+    pub initial_fen: String,
+    /// The clocks of both sides, `None` for games without time control
+    pub clock: Option<ChessClock>,
 }
 
 impl GameState {
     pub fn new_with_default_position() -> Self {
-        Self {
-            board: Board::default_position(),
-            turn: Color::White,
-            additional_board_data: AdditionalBoardData {
+        Self::from_position(
+            Board::default_position(),
+            Color::White,
+            AdditionalBoardData {
                 castling_status: ((true, true), (true, true)),
                 en_passant_square: None,
+                halfmove_clock: 0,
+                fullmove_number: 1,
             },
+        )
+    }
+
+    /// Creates a game starting at the position described by the FEN string
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let (board, turn, additional_board_data) = parse_fen(fen)?;
+        Ok(Self::from_position(board, turn, additional_board_data))
+    }
+
+    /// Returns the FEN string of the current position
+    pub fn to_fen(&self) -> String {
+        to_fen(&self.board, self.turn, &self.additional_board_data)
+    }
+
+    fn from_position(
+        board: Board,
+        turn: Color,
+        additional_board_data: AdditionalBoardData,
+    ) -> Self {
+        let initial_fen = to_fen(&board, turn, &additional_board_data);
+        let mut state = Self {
+            board,
+            turn,
+            additional_board_data,
             selected_square: None,
+            result: GameResult::Ongoing,
+            position_history: Vec::new(),
+            move_history: Vec::new(),
+            redo_stack: Vec::new(),
+            initial_fen,
+            clock: None,
+        };
+        state.record_position();
+        state.update_result();
+        state
+    }
+
+    /// Puts both sides on the base time of the time control and starts the clock
+    /// of the side to move
+    pub fn start_clock(&mut self, time_control: TimeControl, now: Instant) {
+        let mut clock = ChessClock::new(time_control);
+        if !self.result.is_over() {
+            clock.start(self.turn, now);
         }
+        self.clock = Some(clock);
     }
 
-    pub fn switch_turn(&mut self) {
-        if self.turn == Color::White {
-            self.turn = Color::Black;
-        } else {
-            self.turn = Color::White;
+    /// Ends the game if the side to move ran out of time. The opponent wins unless it could
+    /// not checkmate at all, then the game is drawn. Returns `true` if the flag fell.
+    pub fn check_flag(&mut self, now: Instant) -> bool {
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+        if self.result.is_over() || !clock.is_flagged(self.turn, now) {
+            return false;
         }
+        clock.stop(now);
+        let opponent = self.turn.opposite();
+        self.result = if can_checkmate(&self.board, opponent) {
+            GameResult::win_for(opponent, WinReason::Timeout)
+        } else {
+            GameResult::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        };
+        true
+    }
+
+    pub fn switch_turn(&mut self) {
+        self.turn = self.turn.opposite();
     }
 
     /// Moves the piece from `start` to `destination`. This function validates the move fully.
-    /// Returns `false` if the move is invalid and the move was not performed.
-    /// Returns `true` if the move is valid and the piece was moved.
-    pub fn move_piece(&mut self, start: BoardIndex, destination: BoardIndex) -> bool {
-        if self.board.piece_at(start).is_none() {
+    /// `promotion` has to be set if and only if a pawn moves to the last row.
+    /// Returns `false` if the move is invalid or the game is over and the move was not performed.
+    /// Returns `true` if the move is valid and the piece was moved, in which case the turn is
+    /// passed to the other side, the game result is updated and the moves to redo are dropped.
+    pub fn move_piece(
+        &mut self,
+        start: Square,
+        destination: Square,
+        promotion: Option<PieceType>,
+    ) -> bool {
+        let moved = self.make_move(start, destination, promotion);
+        if moved {
+            self.redo_stack.clear();
+        }
+        moved
+    }
+
+    /// Takes back the last move, restoring the position exactly as it was before.
+    /// Returns `false` if there is no move to take back.
+    pub fn undo(&mut self) -> bool {
+        let Some(record) = self.move_history.pop() else {
+            return false;
+        };
+        self.board.set_piece(record.destination, None);
+        self.board.set_piece(record.start, Some(record.piece));
+        if let Some((square, piece)) = record.captured {
+            self.board.set_piece(square, Some(piece));
+        }
+        // put the rook back if the king castled
+        if record.piece.piece_type == PieceType::King
+            && record.start.file().distance(record.destination.file()) == 2
+        {
+            let (rook_start, rook_destination) =
+                castling_rook_squares(record.start, record.destination);
+            self.board
+                .set_piece(rook_start, self.board.piece_at(rook_destination));
+            self.board.set_piece(rook_destination, None);
+        }
+        self.additional_board_data = record.previous_board_data;
+        self.result = record.previous_result;
+        self.switch_turn();
+        self.position_history.pop();
+        // the time already used stays used, only the running clock changes sides
+        if let Some(clock) = &mut self.clock {
+            if self.result.is_over() {
+                clock.stop(Instant::now());
+            } else {
+                clock.start(self.turn, Instant::now());
+            }
+        }
+        self.redo_stack.push(record);
+        true
+    }
+
+    /// Plays the last move taken back with `undo` again.
+    /// Returns `false` if there is no move to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(record) = self.redo_stack.pop() else {
+            return false;
+        };
+        let moved = self.make_move(record.start, record.destination, record.promotion);
+        if moved && let Some(last) = self.move_history.last_mut() {
+            last.comment = record.comment;
+        }
+        moved
+    }
+
+    fn make_move(
+        &mut self,
+        start: Square,
+        destination: Square,
+        promotion: Option<PieceType>,
+    ) -> bool {
+        let now = Instant::now();
+        if self.check_flag(now) || self.board.piece_at(start).is_none() || self.result.is_over() {
             false
         } else {
-            let movement =
-                Movement::from_with_state(start, destination, self, self.additional_board_data);
+            let movement = Movement::from_with_state(
+                start,
+                destination,
+                promotion,
+                self,
+                self.additional_board_data,
+            );
             if validate_move(movement.clone()) {
-                self.board[destination as usize] = self.board[start as usize];
-                self.board[start as usize] = None;
-                self.set_en_passant_square(movement.clone());
-                let en_passant = self.check_for_en_passant(movement.clone());
-                if en_passant {
-                    let modifier: i32 = if movement.movement_info.piece_color == Color::White {
-                        1
+                // a capture on an empty square is en passant, the pawn is next to the start
+                let captured_square = if self.board.piece_at(destination).is_some() {
+                    destination
+                } else {
+                    en_passant_capture_square(start, destination)
+                };
+                self.move_history.push(MoveRecord {
+                    start,
+                    destination,
+                    promotion,
+                    piece: self.board.piece_at(start).unwrap(),
+                    captured: movement
+                        .movement_info
+                        .capturing
+                        .then(|| {
+                            self.board
+                                .piece_at(captured_square)
+                                .map(|p| (captured_square, p))
+                        })
+                        .flatten(),
+                    previous_board_data: self.additional_board_data,
+                    previous_result: self.result,
+                    san: move_to_san(&movement),
+                    comment: None,
+                });
+                play_move(
+                    &mut self.board,
+                    &mut self.additional_board_data,
+                    start,
+                    destination,
+                    promotion,
+                );
+                self.switch_turn();
+                self.record_position();
+                self.update_result();
+                if let Some(clock) = &mut self.clock {
+                    if self.result.is_over() {
+                        clock.stop(now);
                     } else {
-                        -1
-                    };
-                    let mut destination_xy = movement.destination.to_xy();
-                    destination_xy.1 = (destination_xy.1 as i32 + modifier) as u16;
-                    self.board[destination_xy.to_index() as usize] = None;
+                        clock.press(now);
+                    }
                 }
                 true
             } else {
@@ -63,39 +256,189 @@ impl GameState {
         }
     }
 
-    /// Only sets the square if the moved piece was a pawn and it was moved 2 squares, else sets it to None
-    pub fn set_en_passant_square(&mut self, m: Movement) {
-        if m.movement_info.piece_type != PieceType::Pawn {
-            self.additional_board_data.en_passant_square = None;
-            return;
+    /// Returns every legal move of the side to move, with one move per promotion piece
+    pub fn legal_moves(&self) -> Vec<Movement> {
+        generate_legal_moves(&self.board, &self.additional_board_data, self.turn)
+            .into_iter()
+            .map(|m| {
+                Movement::from_with_state(
+                    m.start,
+                    m.destination,
+                    m.promotion,
+                    self,
+                    self.additional_board_data,
+                )
+            })
+            .collect()
+    }
+
+    /// Plays a move given in Standard Algebraic Notation
+    pub fn play_san(&mut self, san: &str) -> Result<(), SanError> {
+        if self.result.is_over() {
+            return Err(SanError::GameOver);
+        }
+        let movement = parse_san(self, san)?;
+        if self.move_piece(movement.start, movement.destination, movement.promotion) {
+            Ok(())
+        } else {
+            Err(SanError::IllegalMove(san.to_string()))
         }
-        let start_xy = m.start.to_xy();
-        let destination_xy = m.destination.to_xy();
-        let y_distance = (start_xy.1 as i32 - destination_xy.1 as i32).abs();
-        if y_distance == 2 {
-            let modifier: i32 = if m.movement_info.piece_color == Color::White {
-                -1
+    }
+
+    /// Checks whether the side to move is checkmated or stalemated, or the game is drawn
+    /// automatically by the seventy-five-move rule, fivefold repetition or insufficient material
+    pub fn update_result(&mut self) {
+        self.result = if !has_legal_moves(&self.board, &self.additional_board_data, self.turn) {
+            if is_in_check(&self.board, self.turn) {
+                GameResult::win_for(self.turn.opposite(), WinReason::Checkmate)
             } else {
-                1
-            };
-            let en_passant_square = (start_xy.0, (start_xy.1 as i32 + modifier) as u16);
-            self.additional_board_data.en_passant_square = Some(en_passant_square.to_index());
+                GameResult::Draw(DrawReason::Stalemate)
+            }
+        } else if self.additional_board_data.halfmove_clock >= SEVENTY_FIVE_MOVE_RULE_HALFMOVES {
+            GameResult::Draw(DrawReason::SeventyFiveMoveRule)
+        } else if self.repetition_count() >= 5 {
+            GameResult::Draw(DrawReason::FivefoldRepetition)
+        } else if is_insufficient_material(&self.board) {
+            GameResult::Draw(DrawReason::InsufficientMaterial)
+        } else {
+            GameResult::Ongoing
+        };
+    }
+
+    /// Returns the reason a draw could be claimed by the side to move right now, if any
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.result.is_over() {
+            None
+        } else if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.additional_board_data.halfmove_clock >= FIFTY_MOVE_RULE_HALFMOVES {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Ends the game in a draw if one can be claimed.
+    /// Returns `true` if the draw was claimed.
+    pub fn claim_draw(&mut self) -> bool {
+        if let Some(reason) = self.claimable_draw() {
+            self.result = GameResult::Draw(reason);
+            if let Some(clock) = &mut self.clock {
+                clock.stop(Instant::now());
+            }
+            true
         } else {
-            self.additional_board_data.en_passant_square = None;
-            return;
+            false
         }
     }
 
-    /// Checks if the movement is en passant
-    pub fn check_for_en_passant(&self, m: Movement) -> bool {
-        // if destination square is None but capturing is true
-        m.movement_info.board.piece_at(m.destination).is_none() && m.movement_info.capturing
+    /// The pieces of `color` captured so far, the most valuable first
+    pub fn captured_pieces(&self, color: Color) -> Vec<Piece> {
+        let mut captured: Vec<Piece> = self
+            .move_history
+            .iter()
+            .filter_map(|record| record.captured.map(|(_, piece)| piece))
+            .filter(|piece| piece.color == color)
+            .collect();
+        captured.sort_by_key(|piece| std::cmp::Reverse(piece.piece_type as usize));
+        captured
+    }
+
+    /// White's material on the board minus black's in pawns, see `PieceType::points`
+    pub fn material_balance(&self) -> i32 {
+        PIECE_TYPES
+            .iter()
+            .map(|piece_type| {
+                let count = |color| self.board.pieces_of(*piece_type, color).count_ones() as i32;
+                piece_type.points() * (count(Color::White) - count(Color::Black))
+            })
+            .sum()
     }
+
+    /// How often the current position occurred in the game, including now
+    pub fn repetition_count(&self) -> usize {
+        let Some(current) = self.position_history.last() else {
+            return 0;
+        };
+        self.position_history
+            .iter()
+            .filter(|position| *position == current)
+            .count()
+    }
+
+    fn record_position(&mut self) {
+        self.position_history.push(PositionKey::new(
+            &self.board,
+            &self.additional_board_data,
+            self.turn,
+        ));
+    }
+
+    /// Returns `true` if moving the piece from `start` to `destination` is a legal pawn move
+    /// to the last row, which needs a promotion piece to be chosen
+    pub fn is_promotion(&self, start: Square, destination: Square) -> bool {
+        self.board
+            .piece_at(start)
+            .is_some_and(|p| p.piece_type == PieceType::Pawn && is_last_row(destination, p.color))
+            && generate_piece_map(&self.board, &self.additional_board_data, self.turn, start)
+                .contains(&destination)
+    }
+}
+
+#[derive(Clone)]
+pub struct MoveRecord {
+    pub start: Square,
+    pub destination: Square,
+    pub promotion: Option<PieceType>,
+    /// The piece that moved, as it was before a promotion
+    pub piece: Piece,
+    /// The captured piece and the square it was taken on, which differs from the
+    /// destination when taking en passant
+    pub captured: Option<(Square, Piece)>,
+    /// En passant square, castling rights and clocks before the move
+    pub previous_board_data: AdditionalBoardData,
+    pub previous_result: GameResult,
+    /// The move in Standard Algebraic Notation
+    pub san: String,
+    /// A comment on the move, exported to PGN
+    pub comment: Option<String>,
 }
 
+/// `((white kingside, white queenside), (black kingside, black queenside))`
+pub type CastlingStatus = ((bool, bool), (bool, bool));
+
 #[derive(Default, Copy, Clone)]
 pub struct AdditionalBoardData {
-    #[allow(dead_code)]
-    pub castling_status: ((bool, bool), (bool, bool)),
-    pub en_passant_square: Option<BoardIndex>,
+    pub castling_status: CastlingStatus,
+    pub en_passant_square: Option<Square>,
+    /// Number of halfmoves since the last capture or pawn move
+    pub halfmove_clock: u32,
+    /// Starts at 1 and is incremented after every move of black
+    pub fullmove_number: u32,
+}
+
+impl AdditionalBoardData {
+    pub fn can_castle(&self, color: Color, kingside: bool) -> bool {
+        let status = match color {
+            Color::White => self.castling_status.0,
+            Color::Black => self.castling_status.1,
+        };
+        if kingside { status.0 } else { status.1 }
+    }
+
+    /// Removes castling rights when a king or rook leaves its starting square
+    /// or a rook is captured on its starting square
+    pub fn update_castling_status(&mut self, start: Square, destination: Square) {
+        for square in [start, destination] {
+            match (square.file(), square.rank()) {
+                (File::E, Rank::FIRST) => self.castling_status.0 = (false, false),
+                (File::H, Rank::FIRST) => self.castling_status.0.0 = false,
+                (File::A, Rank::FIRST) => self.castling_status.0.1 = false,
+                (File::E, Rank::EIGHTH) => self.castling_status.1 = (false, false),
+                (File::H, Rank::EIGHTH) => self.castling_status.1.0 = false,
+                (File::A, Rank::EIGHTH) => self.castling_status.1.1 = false,
+                _ => {}
+            }
+        }
+    }
 }
@@ -0,0 +1,240 @@
+use std::fmt::Display;
+
+use eframe::egui::Color32;
+
+/// Custom themes are read from this file in the working directory if it exists
+pub const THEMES_FILE: &str = "guh-chess-themes.ini";
+
+/// The colors the board is painted in
+#[derive(Clone)]
+pub struct Theme {
+    pub name: String,
+    pub light_square: Color32,
+    pub dark_square: Color32,
+    /// Drawn over the selected square
+    pub selected: Color32,
+    /// Drawn over the squares the selected piece can move to
+    pub legal_move: Color32,
+    /// Drawn over the start and destination of the last move
+    pub last_move: Color32,
+}
+
+impl Theme {
+    fn new(
+        name: &str,
+        light_square: Color32,
+        dark_square: Color32,
+        selected: Color32,
+        legal_move: Color32,
+        last_move: Color32,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            light_square,
+            dark_square,
+            selected,
+            legal_move,
+            last_move,
+        }
+    }
+}
+
+pub fn built_in_themes() -> Vec<Theme> {
+    vec![
+        Theme::new(
+            "Classic",
+            Color32::WHITE,
+            Color32::DARK_GRAY,
+            Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+            Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+            Color32::from_rgba_unmultiplied(255, 200, 0, 96),
+        ),
+        Theme::new(
+            "Wood",
+            Color32::from_rgb(240, 217, 181),
+            Color32::from_rgb(181, 136, 99),
+            Color32::from_rgba_unmultiplied(20, 85, 30, 128),
+            Color32::from_rgba_unmultiplied(20, 85, 30, 96),
+            Color32::from_rgba_unmultiplied(205, 210, 106, 128),
+        ),
+        Theme::new(
+            "Green",
+            Color32::from_rgb(238, 238, 210),
+            Color32::from_rgb(118, 150, 86),
+            Color32::from_rgba_unmultiplied(255, 255, 0, 112),
+            Color32::from_rgba_unmultiplied(0, 0, 0, 64),
+            Color32::from_rgba_unmultiplied(246, 246, 105, 128),
+        ),
+        Theme::new(
+            "Blue",
+            Color32::from_rgb(222, 227, 230),
+            Color32::from_rgb(140, 162, 173),
+            Color32::from_rgba_unmultiplied(255, 170, 0, 128),
+            Color32::from_rgba_unmultiplied(0, 60, 160, 96),
+            Color32::from_rgba_unmultiplied(155, 199, 0, 104),
+        ),
+        Theme::new(
+            "Night",
+            Color32::from_rgb(110, 110, 120),
+            Color32::from_rgb(50, 50, 60),
+            Color32::from_rgba_unmultiplied(200, 60, 60, 128),
+            Color32::from_rgba_unmultiplied(90, 160, 255, 96),
+            Color32::from_rgba_unmultiplied(170, 160, 90, 96),
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeError {
+    /// A `key = value` line before the first `[name]` line
+    MissingName {
+        line: usize,
+    },
+    /// A line that is neither a `[name]`, a `key = value` pair nor a comment
+    InvalidLine {
+        line: usize,
+    },
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+    /// Colors are written as `#rrggbb` or `#rrggbbaa`
+    InvalidColor {
+        line: usize,
+        value: String,
+    },
+    /// Every theme needs at least the light and dark square colors
+    MissingSquareColors {
+        name: String,
+    },
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeError::MissingName { line } => {
+                write!(f, "line {}: colors need a [theme name] above them", line)
+            }
+            ThemeError::InvalidLine { line } => write!(f, "line {}: expected key = value", line),
+            ThemeError::UnknownKey { line, key } => {
+                write!(f, "line {}: unknown key '{}'", line, key)
+            }
+            ThemeError::InvalidColor { line, value } => {
+                write!(f, "line {}: '{}' is not a #rrggbb color", line, value)
+            }
+            ThemeError::MissingSquareColors { name } => {
+                write!(f, "theme '{}' needs light and dark colors", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// Parses themes from an INI-like file, one section per theme:
+///
+/// ```text
+/// [Ocean]
+/// light = #dee3e6
+/// dark = #8ca2ad
+/// selected = #ffaa0080
+/// legal_move = #003ca060
+/// last_move = #9bc70068
+/// ```
+///
+/// The highlight colors are optional and default to the ones of the classic theme.
+/// Lines starting with `#` or `;` are comments.
+pub fn parse_themes(text: &str) -> Result<Vec<Theme>, ThemeError> {
+    let classic = built_in_themes().swap_remove(0);
+    let mut themes = Vec::new();
+    // the theme being read and whether its square colors were set
+    let mut current: Option<(Theme, bool, bool)> = None;
+    for (line_index, line) in text.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            themes.extend(finish_theme(current.take())?);
+            let mut theme = classic.clone();
+            theme.name = name.trim().to_string();
+            current = Some((theme, false, false));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(ThemeError::InvalidLine { line: line_number });
+        };
+        let Some((theme, has_light, has_dark)) = &mut current else {
+            return Err(ThemeError::MissingName { line: line_number });
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let color = parse_color(value).ok_or_else(|| ThemeError::InvalidColor {
+            line: line_number,
+            value: value.to_string(),
+        })?;
+        match key {
+            "light" => {
+                theme.light_square = color;
+                *has_light = true;
+            }
+            "dark" => {
+                theme.dark_square = color;
+                *has_dark = true;
+            }
+            "selected" => theme.selected = color,
+            "legal_move" => theme.legal_move = color,
+            "last_move" => theme.last_move = color,
+            _ => {
+                return Err(ThemeError::UnknownKey {
+                    line: line_number,
+                    key: key.to_string(),
+                });
+            }
+        }
+    }
+    themes.extend(finish_theme(current)?);
+    Ok(themes)
+}
+
+fn finish_theme(current: Option<(Theme, bool, bool)>) -> Result<Option<Theme>, ThemeError> {
+    match current {
+        Some((theme, true, true)) => Ok(Some(theme)),
+        Some((theme, _, _)) => Err(ThemeError::MissingSquareColors { name: theme.name }),
+        None => Ok(None),
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa`
+fn parse_color(value: &str) -> Option<Color32> {
+    let hex = value.strip_prefix('#')?;
+    // `from_str_radix` alone would also accept a sign like in `#+f+f+f`
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color32::from_rgba_unmultiplied(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
+
+/// The built-in themes followed by the ones from `THEMES_FILE`, if it exists
+pub fn load_themes() -> (Vec<Theme>, Option<String>) {
+    let mut themes = built_in_themes();
+    let error = match std::fs::read_to_string(THEMES_FILE) {
+        Ok(text) => match parse_themes(&text) {
+            Ok(custom) => {
+                themes.extend(custom);
+                None
+            }
+            Err(err) => Some(format!("{}: {}", THEMES_FILE, err)),
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => Some(format!("{}: {}", THEMES_FILE, err)),
+    };
+    (themes, error)
+}
@@ -0,0 +1,284 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::Color,
+    engine::search::{Position, SearchInfo, SearchLimits, game_history, search},
+    move_validation::{movement::PlainMove, perft::divide},
+    state::GameState,
+};
+
+/// Time kept in reserve for communication with the GUI, in milliseconds
+const DEFAULT_MOVE_OVERHEAD: u64 = 30;
+/// The number of moves the remaining time is divided by when the GUI does not say
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Runs the engine side of the Universal Chess Interface on stdin and stdout until `quit`
+pub fn run() {
+    let mut engine = UciEngine::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle_command(&line) {
+            break;
+        }
+    }
+    engine.stop_search();
+}
+
+struct UciEngine {
+    /// `None` after a `position` command that could not be set up, until the next valid one
+    state: Option<GameState>,
+    move_overhead: u64,
+    search: Option<RunningSearch>,
+}
+
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The parameters of a `go` command
+#[derive(Default)]
+struct GoParameters {
+    depth: Option<u32>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u64>,
+    infinite: bool,
+}
+
+impl UciEngine {
+    fn new() -> Self {
+        Self {
+            state: Some(GameState::new_with_default_position()),
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            search: None,
+        }
+    }
+
+    /// Handles one line sent by the GUI. Returns `false` once the engine should quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, arguments)) = tokens.split_first() else {
+            return true;
+        };
+        match *command {
+            "uci" => {
+                println!("id name guh-chess {}", env!("CARGO_PKG_VERSION"));
+                println!("id author the guh-chess developers");
+                println!(
+                    "option name Move Overhead type spin default {} min 0 max 5000",
+                    DEFAULT_MOVE_OVERHEAD
+                );
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.state = Some(GameState::new_with_default_position());
+            }
+            "setoption" => self.set_option(arguments),
+            "position" => {
+                self.stop_search();
+                // searching the previous position instead would answer for another game
+                self.state = match parse_position(arguments) {
+                    Ok(state) => Some(state),
+                    Err(err) => {
+                        println!("info string {}", err);
+                        None
+                    }
+                };
+            }
+            "go" => {
+                self.stop_search();
+                match arguments {
+                    ["perft", depth, ..] => self.perft(depth),
+                    _ => self.go(parse_go_parameters(arguments)),
+                }
+            }
+            "stop" => self.stop_search(),
+            "quit" => return false,
+            // `debug` and `register` need no answer, `ponderhit` does not come as the
+            // engine has no `Ponder` option
+            _ => {}
+        }
+        true
+    }
+
+    /// `setoption name <name> [value <value>]`, the name may contain spaces
+    fn set_option(&mut self, arguments: &[&str]) {
+        let value_index = arguments.iter().position(|token| *token == "value");
+        let name = arguments
+            .get(1..value_index.unwrap_or(arguments.len()))
+            .map_or(String::new(), |name| name.join(" "));
+        let value = value_index.map(|index| arguments[index + 1..].join(" "));
+        if name.eq_ignore_ascii_case("Move Overhead") {
+            match value.and_then(|value| value.parse::<u64>().ok()) {
+                Some(overhead) => self.move_overhead = overhead.min(5000),
+                None => println!("info string Move Overhead needs a number"),
+            }
+        } else {
+            println!("info string unknown option '{}'", name);
+        }
+    }
+
+    /// Starts searching the current position on its own thread, which prints the
+    /// `info` lines and finally the `bestmove`. Without a valid position the answer
+    /// is `bestmove 0000` straight away.
+    fn go(&mut self, parameters: GoParameters) {
+        let Some(state) = &self.state else {
+            println!("bestmove 0000");
+            return;
+        };
+        let limits = SearchLimits {
+            depth: parameters.depth,
+            time: self.time_for_move(state.turn, &parameters),
+        };
+        let position = Position::from_state(state);
+        let history = game_history(state);
+        let infinite = parameters.infinite;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            let best_move = search(&position, &history, limits, &thread_stop, print_info);
+            // in infinite mode the best move may only be sent after `stop`
+            while infinite && !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            match best_move {
+                Some(m) => println!("bestmove {}", m.to_long_algebraic()),
+                None => println!("bestmove 0000"),
+            }
+        });
+        self.search = Some(RunningSearch { stop, handle });
+    }
+
+    /// `go perft <depth>` prints the perft count below every legal move of the current
+    /// position and their total, to compare move generation with other engines
+    fn perft(&self, depth: &str) {
+        let Ok(depth) = depth.parse::<u32>() else {
+            println!("info string perft needs a depth");
+            return;
+        };
+        let Some(state) = &self.state else {
+            println!("info string no valid position to count");
+            return;
+        };
+        let start = Instant::now();
+        let divided = divide(
+            &state.board,
+            &state.additional_board_data,
+            state.turn,
+            depth,
+        );
+        for (m, count) in &divided {
+            println!("{}: {}", m.to_long_algebraic(), count);
+        }
+        println!();
+        println!(
+            "Nodes searched: {}",
+            divided.iter().map(|(_, count)| count).sum::<u64>()
+        );
+        println!("Time: {:?}", start.elapsed());
+    }
+
+    /// A fixed `movetime`, or a share of the remaining time plus most of the increment
+    fn time_for_move(&self, turn: Color, parameters: &GoParameters) -> Option<Duration> {
+        if parameters.infinite {
+            return None;
+        }
+        if let Some(movetime) = parameters.movetime {
+            return Some(Duration::from_millis(
+                movetime.saturating_sub(self.move_overhead).max(1),
+            ));
+        }
+        let (time, increment) = match turn {
+            Color::White => (parameters.wtime?, parameters.winc.unwrap_or(0)),
+            Color::Black => (parameters.btime?, parameters.binc.unwrap_or(0)),
+        };
+        let moves_to_go = parameters.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let budget = time / moves_to_go + increment * 3 / 4;
+        let available = time.saturating_sub(self.move_overhead);
+        Some(Duration::from_millis(budget.min(available).max(1)))
+    }
+
+    /// Stops a running search and waits until it sent its best move
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            let _ = search.handle.join();
+        }
+    }
+}
+
+/// `position (startpos | fen <fen>) [moves <move>...]`
+fn parse_position(arguments: &[&str]) -> Result<GameState, String> {
+    let moves_index = arguments
+        .iter()
+        .position(|token| *token == "moves")
+        .unwrap_or(arguments.len());
+    let mut state = match arguments.first() {
+        Some(&"startpos") => GameState::new_with_default_position(),
+        Some(&"fen") => GameState::from_fen(&arguments[1..moves_index].join(" "))
+            .map_err(|err| format!("invalid FEN: {}", err))?,
+        _ => return Err("position needs startpos or fen".to_string()),
+    };
+    for text in arguments.iter().skip(moves_index + 1) {
+        let legal = PlainMove::from_long_algebraic(text)
+            .is_some_and(|m| state.move_piece(m.start, m.destination, m.promotion));
+        if !legal {
+            return Err(format!("illegal move {}", text));
+        }
+    }
+    Ok(state)
+}
+
+fn parse_go_parameters(arguments: &[&str]) -> GoParameters {
+    let mut parameters = GoParameters::default();
+    let mut tokens = arguments.iter();
+    while let Some(token) = tokens.next() {
+        let mut number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+        match *token {
+            "depth" => parameters.depth = number().map(|depth| depth as u32),
+            "movetime" => parameters.movetime = number(),
+            "wtime" => parameters.wtime = number(),
+            "btime" => parameters.btime = number(),
+            "winc" => parameters.winc = number(),
+            "binc" => parameters.binc = number(),
+            "movestogo" => parameters.movestogo = number(),
+            "infinite" => parameters.infinite = true,
+            _ => {}
+        }
+    }
+    parameters
+}
+
+fn print_info(info: &SearchInfo) {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = info.time.as_millis().max(1);
+    let pv: Vec<String> = info.pv.iter().map(|m| m.to_long_algebraic()).collect();
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        info.nodes as u128 * 1000 / millis,
+        info.time.as_millis(),
+        pv.join(" ")
+    );
+}
@@ -1,11 +1,32 @@
-use crate::{BOARD_SQUARE_SIZE, BOARD_SQUARES};
 use eframe::egui::Vec2;
+use guh_chess::BOARD_SQUARES;
 
-pub fn board_size_vec2() -> Vec2 {
-    Vec2::splat((BOARD_SQUARES * BOARD_SQUARE_SIZE) as f32)
+/// The height of the turn info and the controls below the board
+const INFO_HEIGHT: f32 = 80.0;
+/// The smallest square size that leaves enough room for the controls
+pub const MIN_SQUARE_SIZE: f32 = 40.0;
+/// The width of the move list beside the board
+pub const MOVE_LIST_WIDTH: f32 = 180.0;
+
+/// The width and height of the board
+pub fn board_size(square_size: f32) -> f32 {
+    BOARD_SQUARES as f32 * square_size
+}
+
+pub fn board_size_vec2(square_size: f32) -> Vec2 {
+    Vec2::splat(board_size(square_size))
+}
+
+/// The board with the info below it and the move list beside it
+pub fn viewport_size_vec2(square_size: f32) -> Vec2 {
+    let board_size = board_size(square_size);
+    Vec2::new(board_size + MOVE_LIST_WIDTH, board_size + INFO_HEIGHT)
 }
 
-pub fn viewport_size_vec2() -> Vec2 {
-    let board_size = (BOARD_SQUARES * BOARD_SQUARE_SIZE) as f32;
-    Vec2::new(board_size, board_size + 50.0)
+/// The largest whole-pixel square size that fits the board and the info below it
+/// into the available space
+pub fn square_size_for(available: Vec2) -> f32 {
+    (available.x.min(available.y - INFO_HEIGHT) / board_size(1.0))
+        .floor()
+        .max(MIN_SQUARE_SIZE)
 }